@@ -0,0 +1,114 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom};
+
+use super::file::{read_exact_at, write_all_at};
+use super::ByteSource;
+
+/// Reads and writes of block devices start and end on multiples of this.
+pub const SECTOR_SIZE: u64 = 512;
+
+/// A block device such as a whole disk or a partition. Its metadata reports
+/// a length of 0, so the size comes from seeking to the end, and every read
+/// and write covers whole sectors.
+pub struct BlockSource {
+    file: File,
+    path: String,
+    length: u64,
+}
+
+impl BlockSource {
+    pub fn new(mut file: File, path: &str) -> io::Result<BlockSource> {
+        let length = file.seek(SeekFrom::End(0))?;
+        Ok(BlockSource {
+            file,
+            path: path.to_owned(),
+            length,
+        })
+    }
+}
+
+/// The sector aligned range holding `length` bytes from `offset`, cut short
+/// at `size` when the source does not end on a sector boundary.
+fn sectors(offset: u64, length: usize, size: u64) -> (u64, usize) {
+    let start = offset - offset % SECTOR_SIZE;
+    let end = (offset + length as u64)
+        .next_multiple_of(SECTOR_SIZE)
+        .min(size);
+    (start, (end - start) as usize)
+}
+
+impl ByteSource for BlockSource {
+    fn len(&self) -> u64 {
+        self.length
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        if offset + buffer.len() as u64 > self.length {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+
+        let (start, length) = sectors(offset, buffer.len(), self.length);
+        let mut sectors = vec![0; length];
+        read_exact_at(&self.file, start, &mut sectors)?;
+        let skip = (offset - start) as usize;
+        buffer.copy_from_slice(&sectors[skip..skip + buffer.len()]);
+        Ok(())
+    }
+
+    /// Reads the sectors being changed, patches them and writes them back.
+    fn write_at(&self, offset: u64, data: &[u8]) -> io::Result<()> {
+        if offset + data.len() as u64 > self.length {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+
+        let (start, length) = sectors(offset, data.len(), self.length);
+        let mut sectors = vec![0; length];
+        read_exact_at(&self.file, start, &mut sectors)?;
+        let skip = (offset - start) as usize;
+        sectors[skip..skip + data.len()].copy_from_slice(data);
+
+        let file = OpenOptions::new().write(true).open(&self.path)?;
+        write_all_at(&file, start, &sectors)?;
+        file.sync_all()
+    }
+
+    fn saves_in_place(&self) -> bool {
+        true
+    }
+
+    fn label(&self) -> Option<&'static str> {
+        Some("DISK")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_files::temp_file;
+
+    #[test]
+    fn sectors_cover_the_range() {
+        assert_eq!(sectors(0, 512, 4096), (0, 512));
+        assert_eq!(sectors(510, 4, 4096), (0, 1024));
+        assert_eq!(sectors(1024, 1, 4096), (1024, 512));
+        assert_eq!(sectors(1024, 1, 1100), (1024, 76));
+    }
+
+    #[test]
+    fn reads_within_sectors() {
+        let data: Vec<u8> = (0..2048).map(|i| (i % 251) as u8).collect();
+        let file = temp_file(&data);
+        let name = file.path().to_string_lossy();
+
+        let source = BlockSource::new(file.reopen().unwrap(), &name).unwrap();
+        assert_eq!(source.len(), 2048);
+        let mut buffer = [0; 6];
+        source.read_at(509, &mut buffer).unwrap();
+        assert_eq!(buffer, data[509..515]);
+        assert!(source.read_at(2045, &mut buffer).is_err());
+
+        source.write_at(510, &[0xaa, 0x55]).unwrap();
+        source.read_at(509, &mut buffer).unwrap();
+        assert_eq!(buffer[..4], [data[509], 0xaa, 0x55, data[512]]);
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs::{File, Metadata, OpenOptions};
+use std::io;
+
+use memmap2::Mmap;
+
+use super::{copy_from, ByteSource};
+
+/// A file read with positional reads, which need no shared seek position.
+pub struct FileSource {
+    file: File,
+    path: String,
+    length: u64,
+    /// Special files such as devices cannot be replaced by renaming a new
+    /// file over them, so they are saved in place.
+    regular: bool,
+}
+
+impl FileSource {
+    pub fn new(file: File, path: &str, metadata: &Metadata) -> FileSource {
+        FileSource {
+            file,
+            path: path.to_owned(),
+            length: metadata.len(),
+            regular: metadata.is_file(),
+        }
+    }
+}
+
+impl ByteSource for FileSource {
+    fn len(&self) -> u64 {
+        self.length
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        read_exact_at(&self.file, offset, buffer)
+    }
+
+    /// The file is opened for writing only while changes are saved, so it is
+    /// read-only the rest of the time.
+    fn write_at(&self, offset: u64, data: &[u8]) -> io::Result<()> {
+        let file = OpenOptions::new().write(true).open(&self.path)?;
+        write_all_at(&file, offset, data)?;
+        file.sync_all()
+    }
+
+    fn saves_in_place(&self) -> bool {
+        !self.regular
+    }
+}
+
+/// A file mapped read-only into memory. Reads are plain memory accesses, but
+/// another program truncating the file while it is open makes them fault, so
+/// `FileSource` is the safer choice for files that may shrink.
+pub struct MappedSource {
+    map: Mmap,
+}
+
+impl MappedSource {
+    pub fn new(file: &File) -> io::Result<MappedSource> {
+        // Safety: the map is only read. Saving writes a new file and renames
+        // it over the old one, which leaves the mapped data in place, and
+        // `SedecimFileInfo::check_output_path` keeps anything else this
+        // process writes away from the file being viewed. Another program
+        // truncating the file still makes reads fault, which is why reading
+        // through the file is the default.
+        let map = unsafe { Mmap::map(file)? };
+        Ok(MappedSource { map })
+    }
+}
+
+impl ByteSource for MappedSource {
+    fn len(&self) -> u64 {
+        self.map.len() as u64
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        copy_from(&self.map, offset, buffer)
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(&self.map)
+    }
+}
+
+#[cfg(unix)]
+pub(super) fn read_exact_at(file: &File, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buffer, offset)
+}
+
+#[cfg(unix)]
+pub(super) fn write_all_at(file: &File, offset: u64, data: &[u8]) -> io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, data, offset)
+}
+
+#[cfg(windows)]
+pub(super) fn read_exact_at(file: &File, mut offset: u64, mut buffer: &mut [u8]) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    while !buffer.is_empty() {
+        match file.seek_read(buffer, offset)? {
+            0 => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+            read => {
+                buffer = &mut buffer[read..];
+                offset += read as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub(super) fn write_all_at(file: &File, mut offset: u64, mut data: &[u8]) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    while !data.is_empty() {
+        let written = file.seek_write(data, offset)?;
+        data = &data[written..];
+        offset += written as u64;
+    }
+    Ok(())
+}
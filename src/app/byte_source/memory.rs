@@ -0,0 +1,32 @@
+use std::io;
+
+use super::{copy_from, ByteSource};
+
+/// Bytes held in memory.
+pub struct MemorySource {
+    data: Vec<u8>,
+}
+
+impl MemorySource {
+    pub fn new(data: Vec<u8>) -> MemorySource {
+        MemorySource { data }
+    }
+}
+
+impl ByteSource for MemorySource {
+    fn len(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        copy_from(&self.data, offset, buffer)
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(&self.data)
+    }
+
+    fn has_path(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,190 @@
+use std::fs::{self, File};
+use std::io;
+
+mod block;
+mod file;
+mod memory;
+mod process;
+mod stdin;
+
+pub use block::{BlockSource, SECTOR_SIZE};
+pub use file::{FileSource, MappedSource};
+pub use memory::MemorySource;
+pub use process::{MemoryRegion, ProcessSource};
+pub use stdin::StdinSource;
+
+/// File name that reads from standard input.
+pub const STDIN_NAME: &str = "-";
+
+/// Somewhere the bytes being viewed come from. `SedecimFileInfo` and its
+/// page cache only read through this, so a new kind of source needs no
+/// changes to the viewer.
+pub trait ByteSource: Send + Sync {
+    /// Number of bytes in the source.
+    fn len(&self) -> u64;
+
+    /// Fills `buffer` with the bytes from `offset`, failing with
+    /// `UnexpectedEof` when the source ends first.
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()>;
+
+    /// The whole source, when it is held in memory and can be read without
+    /// copying.
+    fn as_slice(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Overwrites bytes in place, for sources saved that way.
+    fn write_at(&self, _offset: u64, _data: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this source cannot be written",
+        ))
+    }
+
+    /// True when saving writes changed bytes back with `write_at` rather than
+    /// replacing the file, e.g. for devices.
+    fn saves_in_place(&self) -> bool {
+        false
+    }
+
+    /// False when the data has no file to be saved back to, so it can only
+    /// be saved somewhere new.
+    fn has_path(&self) -> bool {
+        true
+    }
+
+    /// False when the source is too large to be written out whole, such as
+    /// the address space of a process, most of which is not even mapped.
+    /// Only a bounded range of it can be exported then.
+    fn can_write_whole(&self) -> bool {
+        true
+    }
+
+    /// True when the bytes can change while they are viewed, such as
+    /// process memory, so they are read again rather than cached.
+    fn is_live(&self) -> bool {
+        false
+    }
+
+    /// Short tag shown in the status bar, e.g. "PIPE".
+    fn label(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// The mapped regions of process memory. Addresses outside the readable
+    /// ones hold no data. Empty for sources without gaps.
+    fn regions(&self) -> &[MemoryRegion] {
+        &[]
+    }
+}
+
+/// How a regular file is read, chosen on the command line.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BackendKind {
+    /// Map the file into memory, falling back to `Read` for files that
+    /// cannot be mapped. Scrolling and searching read the mapping in place,
+    /// but the process is killed if the file is truncated while mapped.
+    Mmap,
+    /// Read through the file for every page. The default, as a truncated
+    /// file is then reported rather than fatal.
+    #[default]
+    Read,
+}
+
+impl BackendKind {
+    pub const NAMES: [&'static str; 2] = ["mmap", "read"];
+
+    pub fn parse(text: &str) -> Option<BackendKind> {
+        match text {
+            "mmap" => Some(BackendKind::Mmap),
+            "read" => Some(BackendKind::Read),
+            _ => None,
+        }
+    }
+}
+
+/// Opens the source for `file_name`: standard input for "-", a copy of the
+/// data for named pipes, sector by sector access for block devices, and the
+/// file itself otherwise.
+pub fn open(file_name: &str, kind: BackendKind) -> io::Result<Box<dyn ByteSource>> {
+    if file_name == STDIN_NAME {
+        return Ok(Box::new(StdinSource::read_from(io::stdin().lock())?));
+    }
+
+    let metadata = fs::metadata(file_name)?;
+    let file = File::open(file_name)?;
+    if is_fifo(&metadata) {
+        return Ok(Box::new(StdinSource::read_from(file)?));
+    }
+    if is_block_device(&metadata) {
+        return Ok(Box::new(BlockSource::new(file, file_name)?));
+    }
+
+    // Empty and special files cannot be mapped.
+    if kind == BackendKind::Mmap && metadata.is_file() && metadata.len() > 0 {
+        if let Ok(source) = MappedSource::new(&file) {
+            return Ok(Box::new(source));
+        }
+    }
+
+    Ok(Box::new(FileSource::new(file, file_name, &metadata)))
+}
+
+#[cfg(unix)]
+fn is_fifo(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    metadata.file_type().is_fifo()
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn is_block_device(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    metadata.file_type().is_block_device()
+}
+
+#[cfg(not(unix))]
+fn is_block_device(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Fills `buffer` from the slice of `data` at `offset`.
+fn copy_from(data: &[u8], offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+    let source = usize::try_from(offset)
+        .ok()
+        .and_then(|start| data.get(start..start.checked_add(buffer.len())?))
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    buffer.copy_from_slice(source);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_files::temp_file;
+
+    #[test]
+    fn mapped_and_read_sources_agree() {
+        let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        let file = temp_file(&data);
+        let name = file.path().to_string_lossy();
+
+        let mapped = open(&name, BackendKind::Mmap).unwrap();
+        let read = open(&name, BackendKind::Read).unwrap();
+        assert_eq!(mapped.as_slice(), Some(&data[..]));
+        assert!(read.as_slice().is_none());
+
+        let memory = MemorySource::new(data.clone());
+        for source in [&*mapped, &*read, &memory as &dyn ByteSource] {
+            assert_eq!(source.len(), 1000);
+            let mut buffer = [0; 8];
+            source.read_at(500, &mut buffer).unwrap();
+            assert_eq!(buffer, data[500..508]);
+            assert!(source.read_at(995, &mut buffer).is_err());
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::fs::{self, File};
+use std::io;
+
+use super::file::read_exact_at;
+use super::ByteSource;
+
+/// Addresses from here on are kernel space, such as `[vsyscall]`, and are
+/// left out of the view.
+const USER_SPACE_END: u64 = 1 << 63;
+
+/// Memory is mapped in pages of at least this many bytes.
+const MEMORY_PAGE_SIZE: u64 = 4096;
+
+/// The `errno` for memory that is mapped but cannot be read through
+/// `/proc/<pid>/mem`, such as `[vvar]`.
+const EIO: i32 = 5;
+
+/// One line of `/proc/<pid>/maps`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub end: u64,
+    /// Such as `r-xp`: read, write, execute and private or shared.
+    pub permissions: String,
+    /// The mapped file or a name such as `[heap]`, empty for anonymous
+    /// memory.
+    pub path: String,
+}
+
+impl MemoryRegion {
+    pub fn readable(&self) -> bool {
+        self.permissions.starts_with('r')
+    }
+}
+
+/// The memory of a running process, read through `/proc/<pid>/mem`. Offsets
+/// are virtual addresses; bytes outside the readable regions listed in
+/// `/proc/<pid>/maps`, or in pages of them the kernel refuses to read, read
+/// as zero.
+pub struct ProcessSource {
+    mem: File,
+    regions: Vec<MemoryRegion>,
+    length: u64,
+}
+
+impl ProcessSource {
+    pub fn open(pid: u32) -> io::Result<ProcessSource> {
+        let maps = fs::read_to_string(format!("/proc/{}/maps", pid))?;
+        let regions: Vec<MemoryRegion> = maps
+            .lines()
+            .filter_map(parse_region)
+            .filter(|region| region.end <= USER_SPACE_END)
+            .collect();
+        let mem = File::open(format!("/proc/{}/mem", pid))?;
+        let length = regions.last().map_or(0, |region| region.end);
+
+        Ok(ProcessSource {
+            mem,
+            regions,
+            length,
+        })
+    }
+
+    /// Reads `buffer` from `offset` one memory page at a time, leaving the
+    /// pages that fail with EIO as zeros.
+    fn read_pages(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        let end = offset + buffer.len() as u64;
+        let mut from = offset;
+        while from < end {
+            let to = ((from / MEMORY_PAGE_SIZE + 1) * MEMORY_PAGE_SIZE).min(end);
+            let target = &mut buffer[(from - offset) as usize..(to - offset) as usize];
+            match read_exact_at(&self.mem, from, target) {
+                Err(err) if err.raw_os_error() == Some(EIO) => target.fill(0),
+                result => result?,
+            }
+            from = to;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a line such as
+/// `7f2c1a000000-7f2c1a021000 r-xp 00000000 08:01 1234   /usr/lib/libc.so.6`.
+fn parse_region(line: &str) -> Option<MemoryRegion> {
+    let mut fields = line.splitn(6, ' ');
+    let (start, end) = fields.next()?.split_once('-')?;
+    let permissions = fields.next()?.to_owned();
+    // Offset, device and inode, then the path after some padding.
+    let path = fields.nth(3).unwrap_or_default().trim_start().to_owned();
+
+    Some(MemoryRegion {
+        start: u64::from_str_radix(start, 16).ok()?,
+        end: u64::from_str_radix(end, 16).ok()?,
+        permissions,
+        path,
+    })
+}
+
+impl ByteSource for ProcessSource {
+    fn len(&self) -> u64 {
+        self.length
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        let end = offset + buffer.len() as u64;
+        if end > self.length {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+
+        buffer.fill(0);
+        for region in &self.regions {
+            if !region.readable() || region.end <= offset || region.start >= end {
+                continue;
+            }
+            let from = region.start.max(offset);
+            let to = region.end.min(end);
+            let target = &mut buffer[(from - offset) as usize..(to - offset) as usize];
+            match read_exact_at(&self.mem, from, target) {
+                Err(err) if err.raw_os_error() == Some(EIO) => self.read_pages(from, target)?,
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    fn is_live(&self) -> bool {
+        true
+    }
+
+    fn has_path(&self) -> bool {
+        false
+    }
+
+    fn can_write_whole(&self) -> bool {
+        false
+    }
+
+    fn label(&self) -> Option<&'static str> {
+        Some("PID")
+    }
+
+    fn regions(&self) -> &[MemoryRegion] {
+        &self.regions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_maps_lines() {
+        let region = parse_region(
+            "7f2c1a000000-7f2c1a021000 r-xp 00000000 08:01 1234                       /usr/lib/libc.so.6",
+        )
+        .unwrap();
+        assert_eq!(region.start, 0x7f2c1a000000);
+        assert_eq!(region.end, 0x7f2c1a021000);
+        assert_eq!(region.permissions, "r-xp");
+        assert_eq!(region.path, "/usr/lib/libc.so.6");
+
+        let anonymous = parse_region("7f2c1a021000-7f2c1a023000 rw-p 00000000 00:00 0").unwrap();
+        assert_eq!(anonymous.path, "");
+        assert!(parse_region("not a region").is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reads_own_memory() {
+        let data: Vec<u8> = (0..64).collect();
+        let source = ProcessSource::open(std::process::id()).unwrap();
+        let address = data.as_ptr() as u64;
+        assert!(source
+            .regions()
+            .iter()
+            .any(|region| region.readable() && region.start <= address && address < region.end));
+
+        let mut buffer = [0; 64];
+        source.read_at(address, &mut buffer).unwrap();
+        assert_eq!(buffer[..], data[..]);
+
+        // [vvar] is listed as readable, but reading it fails with EIO.
+        if let Some(vvar) = source
+            .regions()
+            .iter()
+            .find(|region| region.path == "[vvar]")
+        {
+            source.read_at(vvar.start, &mut buffer).unwrap();
+            assert_eq!(buffer, [0; 64]);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::io::{self, Read, Write};
+
+use super::{ByteSource, FileSource, MemorySource};
+
+/// Input kept in memory before it is moved to a temporary file.
+const MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// A copy of data read from standard input or a pipe, which can only be
+/// read once and cannot seek. Small inputs stay in memory; larger ones
+/// spill over into an unnamed temporary file that is removed on exit.
+pub struct StdinSource {
+    spool: Box<dyn ByteSource>,
+}
+
+impl StdinSource {
+    /// Reads `input` to the end.
+    pub fn read_from(mut input: impl Read) -> io::Result<StdinSource> {
+        let mut data = vec![];
+        (&mut input)
+            .take(MEMORY_LIMIT as u64 + 1)
+            .read_to_end(&mut data)?;
+        if data.len() <= MEMORY_LIMIT {
+            return Ok(StdinSource {
+                spool: Box::new(MemorySource::new(data)),
+            });
+        }
+
+        let mut file = tempfile::tempfile()?;
+        file.write_all(&data)?;
+        io::copy(&mut input, &mut file)?;
+        let metadata = file.metadata()?;
+        Ok(StdinSource {
+            spool: Box::new(FileSource::new(file, "", &metadata)),
+        })
+    }
+}
+
+impl ByteSource for StdinSource {
+    fn len(&self) -> u64 {
+        self.spool.len()
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        self.spool.read_at(offset, buffer)
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        self.spool.as_slice()
+    }
+
+    fn has_path(&self) -> bool {
+        false
+    }
+
+    fn label(&self) -> Option<&'static str> {
+        Some("PIPE")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_piped_bytes() {
+        let source = StdinSource::read_from(&b"hello pipe"[..]).unwrap();
+        assert_eq!(source.len(), 10);
+
+        let mut buffer = [0; 4];
+        source.read_at(6, &mut buffer).unwrap();
+        assert_eq!(&buffer, b"pipe");
+        assert!(source.read_at(8, &mut buffer).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use std::io;
+
+use sha2::{Digest, Sha256};
+
+use super::sedecim_file_info::SedecimFileInfo;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const ADLER_MODULUS: u32 = 65521;
+
+/// Running checksums of a stream of bytes.
+pub struct Checksums {
+    crc32: crc32fast::Hasher,
+    adler_a: u32,
+    adler_b: u32,
+    sum: u8,
+    sha256: Sha256,
+}
+
+impl Checksums {
+    pub fn new() -> Checksums {
+        Checksums {
+            crc32: crc32fast::Hasher::new(),
+            adler_a: 1,
+            adler_b: 0,
+            sum: 0,
+            sha256: Sha256::new(),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.crc32.update(data);
+        self.sha256.update(data);
+        for value in data {
+            self.adler_a = (self.adler_a + *value as u32) % ADLER_MODULUS;
+            self.adler_b = (self.adler_b + self.adler_a) % ADLER_MODULUS;
+            self.sum = self.sum.wrapping_add(*value);
+        }
+    }
+
+    /// Formats every checksum on one line for the status bar.
+    pub fn finish(self) -> String {
+        let sha256: String = self
+            .sha256
+            .finalize()
+            .iter()
+            .map(|value| format!("{:02x}", value))
+            .collect();
+
+        format!(
+            "CRC-32 {:08x} | Adler-32 {:08x} | Sum-8 {:02x} | SHA-256 {}",
+            self.crc32.finalize(),
+            (self.adler_b << 16) | self.adler_a,
+            self.sum,
+            sha256
+        )
+    }
+}
+
+/// Computes the checksums of `length` bytes from `start` with all edits
+/// applied.
+pub fn checksum_range(info: &SedecimFileInfo, start: u64, length: u64) -> io::Result<String> {
+    let mut checksums = Checksums::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut offset = start;
+    let end = start + length;
+
+    while offset < end {
+        let wanted = ((end - offset) as usize).min(CHUNK_SIZE);
+        let read = info.read_at(offset, &mut buffer[..wanted])?;
+        if read == 0 {
+            break;
+        }
+
+        checksums.update(&buffer[..read]);
+        offset += read as u64;
+    }
+
+    Ok(checksums.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_values() {
+        let mut checksums = Checksums::new();
+        checksums.update(b"1234");
+        checksums.update(b"56789");
+        let text = checksums.finish();
+
+        assert!(text.starts_with("CRC-32 cbf43926 | Adler-32 091e01de | Sum-8 dd"));
+        assert!(text.ends_with("15e2b0d3c33891ebb0f1ef609ec419420c20e320ce94c65fbc8c3312448eb225"));
+    }
+}
@@ -0,0 +1,308 @@
+use std::io;
+
+use super::diff::{self, Hunk};
+use super::sedecim_file_info::SedecimFileInfo;
+
+/// Bytes compared per read.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// A second file shown beside the one being viewed, compared with it byte
+/// by byte at the same offsets, or lined up around inserted and deleted
+/// bytes once aligned. Only the first file can be edited.
+pub struct Compare {
+    pub file_info: SedecimFileInfo,
+    /// How the files line up, when aligned.
+    pub alignment: Option<Vec<Hunk>>,
+    /// Revision of the first file the alignment was worked out for.
+    pub aligned_revision: u64,
+}
+
+impl Compare {
+    pub fn new(file_info: SedecimFileInfo) -> Compare {
+        Compare {
+            file_info,
+            alignment: None,
+            aligned_revision: 0,
+        }
+    }
+
+    /// The alignment, unless the first file has changed since it was worked
+    /// out. `revision` is the first file's current revision.
+    pub fn current_alignment(&self, revision: u64) -> Option<&[Hunk]> {
+        self.alignment
+            .as_deref()
+            .filter(|_| self.aligned_revision == revision)
+    }
+
+    /// Offset in this file of the byte drawn beside `address` of the first:
+    /// its counterpart once aligned, otherwise the same offset. `None` when
+    /// this file has no byte lined up with it, such as a deleted one.
+    pub fn counterpart(&self, revision: u64, address: u64) -> Option<u64> {
+        match self.current_alignment(revision) {
+            Some(hunks) => diff::counterpart(hunks, address),
+            None => Some(address),
+        }
+    }
+
+    /// Offset in this file of the first byte drawn beside the `length` bytes
+    /// from `address` of the first, such as the start of a row.
+    pub fn first_counterpart(&self, revision: u64, address: u64, length: u64) -> Option<u64> {
+        (address..address + length).find_map(|address| self.counterpart(revision, address))
+    }
+
+    /// Where this file's view starts beside a view of `length` bytes from
+    /// `address` of the first, so the bytes read around it are the ones
+    /// drawn. With nothing lined up on screen it is where the hunk there
+    /// starts in this file.
+    pub fn view_offset(&self, revision: u64, address: u64, length: u64) -> u64 {
+        self.first_counterpart(revision, address, length)
+            .or_else(|| {
+                let hunks = self.current_alignment(revision)?;
+                diff::hunk_at(hunks, address).map(|hunk| hunk.b_start)
+            })
+            .unwrap_or(address)
+    }
+
+    /// Whether the files were aligned before the first file last changed.
+    pub fn is_alignment_stale(&self, revision: u64) -> bool {
+        self.alignment.is_some() && self.aligned_revision != revision
+    }
+}
+
+/// A run of offsets where the two files differ.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffRegion {
+    pub start: u64,
+    pub length: u64,
+}
+
+impl DiffRegion {
+    pub fn end(&self) -> u64 {
+        self.start + self.length
+    }
+}
+
+/// Compares the bytes from `start` up to `end`, returning whether each one
+/// differs. A byte that only one of the files has counts as different.
+fn compare_chunk(
+    a: &SedecimFileInfo,
+    b: &SedecimFileInfo,
+    start: u64,
+    end: u64,
+) -> io::Result<Vec<bool>> {
+    let length = (end - start) as usize;
+    let mut left = vec![0; length];
+    let mut right = vec![0; length];
+    let left_read = a.read_at(start, &mut left)?;
+    let right_read = b.read_at(start, &mut right)?;
+
+    Ok((0..length)
+        .map(|index| (index < left_read) != (index < right_read) || left[index] != right[index])
+        .collect())
+}
+
+/// Offsets compared: up to the end of the longer file.
+fn compared_size(a: &SedecimFileInfo, b: &SedecimFileInfo) -> u64 {
+    a.file_size.max(b.file_size)
+}
+
+/// Lists every region where the files differ, including the tail of the
+/// longer file.
+pub fn diff_regions(a: &SedecimFileInfo, b: &SedecimFileInfo) -> io::Result<Vec<DiffRegion>> {
+    let size = compared_size(a, b);
+    let mut regions: Vec<DiffRegion> = vec![];
+    let mut position = 0;
+    while position < size {
+        let end = (position + CHUNK_SIZE).min(size);
+        for (index, differs) in compare_chunk(a, b, position, end)?.into_iter().enumerate() {
+            if !differs {
+                continue;
+            }
+            let offset = position + index as u64;
+            match regions.last_mut() {
+                Some(region) if region.end() == offset => region.length += 1,
+                _ => regions.push(DiffRegion {
+                    start: offset,
+                    length: 1,
+                }),
+            }
+        }
+        position = end;
+    }
+
+    Ok(regions)
+}
+
+/// Finds the start of the first differing region after the one holding
+/// `from`, if `from` is in one.
+pub fn next_difference(
+    a: &SedecimFileInfo,
+    b: &SedecimFileInfo,
+    from: u64,
+) -> io::Result<Option<u64>> {
+    let size = compared_size(a, b);
+    let mut in_region = true;
+    let mut position = from;
+    while position < size {
+        let end = (position + CHUNK_SIZE).min(size);
+        for (index, differs) in compare_chunk(a, b, position, end)?.into_iter().enumerate() {
+            if !differs {
+                in_region = false;
+            } else if !in_region {
+                return Ok(Some(position + index as u64));
+            }
+        }
+        position = end;
+    }
+
+    Ok(None)
+}
+
+/// Finds the start of the closest differing region that starts before
+/// `from`.
+pub fn previous_difference(
+    a: &SedecimFileInfo,
+    b: &SedecimFileInfo,
+    from: u64,
+) -> io::Result<Option<u64>> {
+    let mut found = None;
+    let mut end = from.min(compared_size(a, b));
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK_SIZE);
+        let flags = compare_chunk(a, b, start, end)?;
+        for (index, differs) in flags.into_iter().enumerate().rev() {
+            match (differs, found) {
+                // Still walking back through equal bytes.
+                (false, None) => {}
+                // The region found starts after this equal byte.
+                (false, Some(_)) => return Ok(found),
+                (true, _) => found = Some(start + index as u64),
+            }
+        }
+        end = start;
+    }
+
+    Ok(found)
+}
+
+/// Describes the differences in one line for the status bar.
+pub fn summarize(regions: &[DiffRegion]) -> String {
+    let (Some(first), Some(last)) = (regions.first(), regions.last()) else {
+        return "The files are identical.".to_owned();
+    };
+
+    let total: u64 = regions.iter().map(|region| region.length).sum();
+    // The first of equally large regions.
+    let largest = regions
+        .iter()
+        .rev()
+        .max_by_key(|region| region.length)
+        .unwrap_or(first);
+    format!(
+        "{} differing region(s), {} byte(s) from {:06x} to {:06x}, largest {} byte(s) at {:06x}",
+        regions.len(),
+        total,
+        first.start,
+        last.end() - 1,
+        largest.length,
+        largest.start
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::diff;
+    use crate::app::test_files::open;
+
+    #[test]
+    fn finds_differing_regions() {
+        let (a, _a_file) = open(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let (b, _b_file) = open(&[1, 9, 9, 4, 5, 9, 7, 8, 0, 0]);
+
+        let regions = diff_regions(&a, &b).unwrap();
+        assert_eq!(
+            regions,
+            [
+                DiffRegion {
+                    start: 1,
+                    length: 2
+                },
+                DiffRegion {
+                    start: 5,
+                    length: 1
+                },
+                DiffRegion {
+                    start: 8,
+                    length: 2
+                },
+            ]
+        );
+        assert_eq!(
+            summarize(&regions),
+            "3 differing region(s), 5 byte(s) from 000001 to 000009, largest 2 byte(s) at 000001"
+        );
+    }
+
+    #[test]
+    fn steps_between_differences() {
+        let (a, _a_file) = open(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let (b, _b_file) = open(&[1, 9, 9, 4, 5, 9, 7, 8]);
+
+        assert_eq!(next_difference(&a, &b, 0).unwrap(), Some(1));
+        assert_eq!(next_difference(&a, &b, 1).unwrap(), Some(5));
+        assert_eq!(next_difference(&a, &b, 2).unwrap(), Some(5));
+        assert_eq!(next_difference(&a, &b, 5).unwrap(), None);
+
+        assert_eq!(previous_difference(&a, &b, 7).unwrap(), Some(5));
+        assert_eq!(previous_difference(&a, &b, 5).unwrap(), Some(1));
+        assert_eq!(previous_difference(&a, &b, 2).unwrap(), Some(1));
+        assert_eq!(previous_difference(&a, &b, 1).unwrap(), None);
+    }
+
+    #[test]
+    fn identical_files_have_no_regions() {
+        let (a, _a_file) = open(b"same");
+        let (b, _b_file) = open(b"same");
+
+        assert!(diff_regions(&a, &b).unwrap().is_empty());
+        assert_eq!(summarize(&[]), "The files are identical.");
+    }
+
+    #[test]
+    fn rows_show_the_counterparts_of_the_first_file() {
+        let (a, _a_file) = open(b"0123456789abcdefghij");
+        let (b, _b_file) = open(b"0123NEW456789cdefghij");
+        let mut compare = Compare::new(b);
+        let revision = a.revision();
+        assert_eq!(compare.first_counterpart(revision, 8, 4), Some(8));
+
+        compare.alignment = Some(diff::align_files(&a, &compare.file_info).unwrap());
+        compare.aligned_revision = revision;
+        // Rows of 4 bytes: the insertion before 4 and the deletion of "ab"
+        // shift the rows after them.
+        let rows: Vec<Option<u64>> = (0..5)
+            .map(|row| compare.first_counterpart(revision, row * 4, 4))
+            .collect();
+        assert_eq!(rows, [Some(0), Some(7), Some(11), Some(13), Some(17)]);
+        assert_eq!(compare.counterpart(revision, 10), None);
+        assert_eq!(compare.counterpart(revision, 12), Some(13));
+
+        assert_eq!(compare.view_offset(revision, 8, 8), 11);
+        assert_eq!(compare.view_offset(revision, 10, 2), 13);
+    }
+
+    #[test]
+    fn edits_make_the_alignment_stale() {
+        let (mut a, _a_file) = open(b"abcd");
+        let (b, _b_file) = open(b"abd");
+        let mut compare = Compare::new(b);
+        compare.alignment = Some(diff::align_files(&a, &compare.file_info).unwrap());
+        compare.aligned_revision = a.revision();
+        assert!(compare.current_alignment(a.revision()).is_some());
+
+        a.set_byte(0, b'x').unwrap();
+        assert!(compare.current_alignment(a.revision()).is_none());
+        assert!(compare.is_alignment_stale(a.revision()));
+    }
+}
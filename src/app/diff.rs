@@ -0,0 +1,827 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::ops::Range;
+
+use super::sedecim_file_info::SedecimFileInfo;
+
+/// Differing stretches up to this long on both sides are aligned byte by
+/// byte; longer ones are reported as one change.
+const MYERS_MAX_SIZE: usize = 64 * 1024;
+
+/// Edits a byte by byte alignment may take before the stretch is reported
+/// as one change instead.
+const MYERS_MAX_EDITS: usize = 1024;
+
+/// Smallest block of the first file looked for in the second.
+const MIN_BLOCK_SIZE: usize = 32;
+
+/// Most blocks hashed in the first file; larger files use larger blocks.
+const MAX_BLOCKS: usize = 1 << 20;
+
+/// Most bytes read from either file at once while aligning them.
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Multiplier of the rolling block hash.
+const HASH_BASE: u64 = 0x100_0000_01b3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HunkKind {
+    /// The same bytes in both files.
+    Equal,
+    /// Bytes only the second file has.
+    Insert,
+    /// Bytes only the first file has.
+    Delete,
+    /// Bytes of the first file replaced by different bytes in the second.
+    Change,
+}
+
+impl HunkKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            HunkKind::Equal => "equal",
+            HunkKind::Insert => "insert",
+            HunkKind::Delete => "delete",
+            HunkKind::Change => "change",
+        }
+    }
+}
+
+/// A stretch of the first file and the stretch of the second one it lines
+/// up with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hunk {
+    pub kind: HunkKind,
+    pub a_start: u64,
+    pub a_length: u64,
+    pub b_start: u64,
+    pub b_length: u64,
+}
+
+impl Hunk {
+    pub fn a_end(&self) -> u64 {
+        self.a_start + self.a_length
+    }
+}
+
+/// Collects hunks in order, joining neighbours of the same kind and any
+/// edits next to each other into one change.
+#[derive(Default)]
+struct Hunks {
+    hunks: Vec<Hunk>,
+    a_position: u64,
+    b_position: u64,
+}
+
+impl Hunks {
+    fn equal(&mut self, length: u64) {
+        self.push(HunkKind::Equal, length, length);
+    }
+
+    fn edit(&mut self, a_length: u64, b_length: u64) {
+        let kind = match (a_length, b_length) {
+            (_, 0) => HunkKind::Delete,
+            (0, _) => HunkKind::Insert,
+            _ => HunkKind::Change,
+        };
+        self.push(kind, a_length, b_length);
+    }
+
+    fn push(&mut self, kind: HunkKind, a_length: u64, b_length: u64) {
+        if a_length == 0 && b_length == 0 {
+            return;
+        }
+        self.a_position += a_length;
+        self.b_position += b_length;
+
+        if let Some(last) = self.hunks.last_mut() {
+            if (last.kind == HunkKind::Equal) == (kind == HunkKind::Equal) {
+                last.a_length += a_length;
+                last.b_length += b_length;
+                if kind != HunkKind::Equal && last.a_length > 0 && last.b_length > 0 {
+                    last.kind = HunkKind::Change;
+                }
+                return;
+            }
+        }
+
+        self.hunks.push(Hunk {
+            kind,
+            a_start: self.a_position - a_length,
+            a_length,
+            b_start: self.b_position - b_length,
+            b_length,
+        });
+    }
+}
+
+/// Data being aligned, read a piece at a time so neither file has to be
+/// held in memory whole.
+trait Data {
+    fn size(&self) -> u64;
+
+    /// Fills `buffer` with the bytes from `start`.
+    fn read(&self, start: u64, buffer: &mut [u8]) -> io::Result<()>;
+
+    fn load(&self, range: Range<u64>) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0; (range.end - range.start) as usize];
+        self.read(range.start, &mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+impl Data for &[u8] {
+    fn size(&self) -> u64 {
+        self.len() as u64
+    }
+
+    fn read(&self, start: u64, buffer: &mut [u8]) -> io::Result<()> {
+        let start = start as usize;
+        buffer.copy_from_slice(&self[start..start + buffer.len()]);
+        Ok(())
+    }
+}
+
+impl Data for SedecimFileInfo {
+    fn size(&self) -> u64 {
+        self.file_size
+    }
+
+    fn read(&self, start: u64, buffer: &mut [u8]) -> io::Result<()> {
+        if self.read_at(start, buffer)? < buffer.len() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+}
+
+/// The part of some `Data` last read, for reading through it in order.
+struct Window<'a> {
+    data: &'a dyn Data,
+    start: u64,
+    bytes: Vec<u8>,
+}
+
+impl<'a> Window<'a> {
+    fn new(data: &'a dyn Data) -> Window<'a> {
+        Window {
+            data,
+            start: 0,
+            bytes: vec![],
+        }
+    }
+
+    /// The `length` bytes from `start`, reading them and the chunk after
+    /// them in when they are not already held.
+    fn get(&mut self, start: u64, length: usize) -> io::Result<&[u8]> {
+        let end = start + length as u64;
+        if start < self.start || end > self.start + self.bytes.len() as u64 {
+            let read = (length.max(CHUNK_SIZE) as u64).min(self.data.size() - start);
+            self.bytes.resize(read as usize, 0);
+            self.data.read(start, &mut self.bytes)?;
+            self.start = start;
+        }
+        let offset = (start - self.start) as usize;
+        Ok(&self.bytes[offset..offset + length])
+    }
+}
+
+/// Aligns two files, with all edits applied, allowing for bytes inserted
+/// into or deleted from either one. The files are read in chunks, and only
+/// the stretches aligned byte by byte are held in memory.
+pub fn align_files(a: &SedecimFileInfo, b: &SedecimFileInfo) -> io::Result<Vec<Hunk>> {
+    align_data(a, b)
+}
+
+/// Lines up `a` with `b`. The common start and end are matched first; the
+/// rest is aligned byte by byte when it is small, otherwise around blocks
+/// of `a` found unchanged in `b`.
+pub fn align(a: &[u8], b: &[u8]) -> Vec<Hunk> {
+    align_data(&a, &b).expect("data in memory can always be read")
+}
+
+fn align_data(a: &dyn Data, b: &dyn Data) -> io::Result<Vec<Hunk>> {
+    let (a_size, b_size) = (a.size(), b.size());
+    let prefix = common_after(a, 0, b, 0, a_size.min(b_size))?;
+    let suffix = common_before(a, a_size, b, b_size, a_size.min(b_size) - prefix)?;
+    let a_middle = prefix..a_size - suffix;
+    let b_middle = prefix..b_size - suffix;
+
+    let mut hunks = Hunks::default();
+    hunks.equal(prefix);
+    if fits_myers(&a_middle, &b_middle) {
+        align_gap(a, a_middle, b, b_middle, &mut hunks)?;
+    } else {
+        let mut a_end = a_middle.start;
+        let mut b_end = b_middle.start;
+        for (a_start, b_start, length) in anchors(a, a_middle.clone(), b, b_middle.clone())? {
+            align_gap(a, a_end..a_start, b, b_end..b_start, &mut hunks)?;
+            hunks.equal(length);
+            a_end = a_start + length;
+            b_end = b_start + length;
+        }
+        align_gap(a, a_end..a_middle.end, b, b_end..b_middle.end, &mut hunks)?;
+    }
+    hunks.equal(suffix);
+
+    Ok(hunks.hunks)
+}
+
+fn fits_myers(a: &Range<u64>, b: &Range<u64>) -> bool {
+    a.end - a.start <= MYERS_MAX_SIZE as u64 && b.end - b.start <= MYERS_MAX_SIZE as u64
+}
+
+/// Aligns a stretch between matches byte by byte, or reports it as one
+/// change when it is too long or too different.
+fn align_gap(
+    a: &dyn Data,
+    a_range: Range<u64>,
+    b: &dyn Data,
+    b_range: Range<u64>,
+    hunks: &mut Hunks,
+) -> io::Result<()> {
+    let script = if fits_myers(&a_range, &b_range) {
+        myers(
+            &a.load(a_range.clone())?,
+            &b.load(b_range.clone())?,
+            MYERS_MAX_EDITS,
+        )
+    } else {
+        None
+    };
+
+    match script {
+        Some(script) => {
+            for (kind, length) in script {
+                let length = length as u64;
+                match kind {
+                    HunkKind::Equal => hunks.equal(length),
+                    HunkKind::Delete => hunks.edit(length, 0),
+                    _ => hunks.edit(0, length),
+                }
+            }
+        }
+        None => hunks.edit(a_range.end - a_range.start, b_range.end - b_range.start),
+    }
+    Ok(())
+}
+
+/// How many bytes from `a_start` and `b_start` are the same, up to `limit`.
+/// They are compared in pieces that start small, as most runs are short.
+fn common_after(
+    a: &dyn Data,
+    a_start: u64,
+    b: &dyn Data,
+    b_start: u64,
+    limit: u64,
+) -> io::Result<u64> {
+    let (mut a_bytes, mut b_bytes) = (vec![], vec![]);
+    let mut step = MIN_BLOCK_SIZE;
+    let mut length = 0;
+    while length < limit {
+        let size = (limit - length).min(step as u64) as usize;
+        a_bytes.resize(size, 0);
+        b_bytes.resize(size, 0);
+        a.read(a_start + length, &mut a_bytes)?;
+        b.read(b_start + length, &mut b_bytes)?;
+        let same = a_bytes
+            .iter()
+            .zip(&b_bytes)
+            .take_while(|(x, y)| x == y)
+            .count();
+        length += same as u64;
+        if same < size {
+            break;
+        }
+        step = (step * 2).min(CHUNK_SIZE);
+    }
+    Ok(length)
+}
+
+/// How many bytes just before `a_end` and `b_end` are the same, up to
+/// `limit`.
+fn common_before(
+    a: &dyn Data,
+    a_end: u64,
+    b: &dyn Data,
+    b_end: u64,
+    limit: u64,
+) -> io::Result<u64> {
+    let (mut a_bytes, mut b_bytes) = (vec![], vec![]);
+    let mut step = MIN_BLOCK_SIZE;
+    let mut length = 0;
+    while length < limit {
+        let size = (limit - length).min(step as u64) as usize;
+        a_bytes.resize(size, 0);
+        b_bytes.resize(size, 0);
+        a.read(a_end - length - size as u64, &mut a_bytes)?;
+        b.read(b_end - length - size as u64, &mut b_bytes)?;
+        let same = a_bytes
+            .iter()
+            .rev()
+            .zip(b_bytes.iter().rev())
+            .take_while(|(x, y)| x == y)
+            .count();
+        length += same as u64;
+        if same < size {
+            break;
+        }
+        step = (step * 2).min(CHUNK_SIZE);
+    }
+    Ok(length)
+}
+
+/// Finds stretches that are the same in both files, in order in both. Every
+/// block of `a` that occurs once is looked up at each offset of `b` with a
+/// rolling hash, the longest chain of matches in order is kept, and each
+/// match is then grown towards its neighbours.
+///
+/// Returns the start in `a`, the start in `b` and the length of each match.
+fn anchors(
+    a: &dyn Data,
+    a_range: Range<u64>,
+    b: &dyn Data,
+    b_range: Range<u64>,
+) -> io::Result<Vec<(u64, u64, u64)>> {
+    let a_size = a_range.end - a_range.start;
+    let block = (a_size / MAX_BLOCKS as u64).max(MIN_BLOCK_SIZE as u64);
+    if a_size < block || b_range.end - b_range.start < block {
+        return Ok(vec![]);
+    }
+    let block_length = block as usize;
+
+    // Blocks that repeat, such as padding, would match anywhere.
+    let mut blocks: HashMap<u64, Option<u64>> = HashMap::new();
+    let mut chunk = vec![0; block_length * (CHUNK_SIZE / block_length).max(1)];
+    let mut start = a_range.start;
+    while start + block <= a_range.end {
+        let length = (chunk.len() as u64).min((a_range.end - start) / block * block) as usize;
+        a.read(start, &mut chunk[..length])?;
+        for (index, data) in chunk[..length].chunks_exact(block_length).enumerate() {
+            blocks
+                .entry(block_hash(data))
+                .and_modify(|found| *found = None)
+                .or_insert(Some(start + (index * block_length) as u64));
+        }
+        start += length as u64;
+    }
+
+    let top = HASH_BASE.wrapping_pow(block as u32 - 1);
+    let mut found = vec![];
+    let mut a_block = vec![0; block_length];
+    let mut b_window = Window::new(b);
+    let mut position = b_range.start;
+    let mut hash = block_hash(b_window.get(position, block_length)?);
+    loop {
+        let matched = match blocks.get(&hash) {
+            Some(Some(start)) => {
+                a.read(*start, &mut a_block)?;
+                (a_block[..] == *b_window.get(position, block_length)?).then_some(*start)
+            }
+            _ => None,
+        };
+        if let Some(start) = matched {
+            found.push((start, position));
+            position += block;
+            if position + block > b_range.end {
+                break;
+            }
+            hash = block_hash(b_window.get(position, block_length)?);
+        } else {
+            if position + block >= b_range.end {
+                break;
+            }
+            let bytes = b_window.get(position, block_length + 1)?;
+            hash = hash
+                .wrapping_sub((bytes[0] as u64).wrapping_mul(top))
+                .wrapping_mul(HASH_BASE)
+                .wrapping_add(bytes[block_length] as u64);
+            position += 1;
+        }
+    }
+
+    // Join neighbouring blocks into longer matches.
+    let mut matches: Vec<(u64, u64, u64)> = vec![];
+    for (a_start, b_start) in longest_chain(&found) {
+        match matches.last_mut() {
+            Some(last) if last.0 + last.2 == a_start && last.1 + last.2 == b_start => {
+                last.2 += block
+            }
+            _ => matches.push((a_start, b_start, block)),
+        }
+    }
+
+    // Grow each match over equal bytes, up to its neighbours.
+    let (mut a_floor, mut b_floor) = (a_range.start, b_range.start);
+    for index in 0..matches.len() {
+        let (a_start, b_start, length) = matches[index];
+        let before = common_before(
+            a,
+            a_start,
+            b,
+            b_start,
+            (a_start - a_floor).min(b_start - b_floor),
+        )?;
+        let (a_end, b_end) = (a_start + length, b_start + length);
+        let (a_limit, b_limit) = match matches.get(index + 1) {
+            Some(next) => (next.0, next.1),
+            None => (a_range.end, b_range.end),
+        };
+        let after = common_after(a, a_end, b, b_end, (a_limit - a_end).min(b_limit - b_end))?;
+        matches[index] = (a_start - before, b_start - before, before + length + after);
+        a_floor = a_end + after;
+        b_floor = b_end + after;
+    }
+
+    Ok(matches)
+}
+
+fn block_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0u64, |hash, value| {
+        hash.wrapping_mul(HASH_BASE).wrapping_add(*value as u64)
+    })
+}
+
+/// The longest run of `(a, b)` pairs, given in order of `b`, whose `a` also
+/// only increases.
+fn longest_chain(pairs: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    // `tails[n]` is the index of the pair ending the best chain of n + 1
+    // pairs found so far, the one with the smallest `a`.
+    let mut tails: Vec<usize> = vec![];
+    let mut previous = vec![None; pairs.len()];
+    for (index, (a, _)) in pairs.iter().enumerate() {
+        let length = tails.partition_point(|tail| pairs[*tail].0 < *a);
+        if length > 0 {
+            previous[index] = Some(tails[length - 1]);
+        }
+        if length == tails.len() {
+            tails.push(index);
+        } else {
+            tails[length] = index;
+        }
+    }
+
+    let mut chain = vec![];
+    let mut next = tails.last().copied();
+    while let Some(index) = next {
+        chain.push(pairs[index]);
+        next = previous[index];
+    }
+    chain.reverse();
+    chain
+}
+
+/// The fewest edits turning `a` into `b`, as runs of equal, deleted and
+/// inserted bytes, or `None` when that takes more than `max_edits`. This is
+/// Myers' O(ND) algorithm, keeping each round's furthest reaching paths to
+/// walk back along.
+fn myers(a: &[u8], b: &[u8], max_edits: usize) -> Option<Vec<(HunkKind, usize)>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = max_edits.min(a.len() + b.len()) as isize;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    let mut trace = vec![];
+
+    for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m, offset));
+            }
+        }
+    }
+
+    None
+}
+
+/// Walks back from the end through the paths kept by `myers`.
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize, offset: isize) -> Vec<(HunkKind, usize)> {
+    let mut steps = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let index = (k + offset) as usize;
+        let previous_k = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = v[(previous_k + offset) as usize];
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            steps.push(HunkKind::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            steps.push(if x == previous_x {
+                HunkKind::Insert
+            } else {
+                HunkKind::Delete
+            });
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+
+    let mut runs: Vec<(HunkKind, usize)> = vec![];
+    for kind in steps.into_iter().rev() {
+        match runs.last_mut() {
+            Some((last, length)) if *last == kind => *length += 1,
+            _ => runs.push((kind, 1)),
+        }
+    }
+    runs
+}
+
+/// The hunk covering `address` of the first file, if any.
+pub fn hunk_at(hunks: &[Hunk], address: u64) -> Option<&Hunk> {
+    let index = hunks.partition_point(|hunk| hunk.a_end() <= address);
+    hunks.get(index).filter(|hunk| hunk.a_start <= address)
+}
+
+/// Bytes of the second file inserted just before `address` of the first.
+pub fn insert_before(hunks: &[Hunk], address: u64) -> Option<&Hunk> {
+    let index = hunks.partition_point(|hunk| hunk.a_end() < address);
+    hunks[index..]
+        .iter()
+        .take_while(|hunk| hunk.a_start <= address)
+        .find(|hunk| hunk.kind == HunkKind::Insert && hunk.a_start == address)
+}
+
+/// The offset in the second file of the byte lined up with `address` of the
+/// first, if it has one.
+pub fn counterpart(hunks: &[Hunk], address: u64) -> Option<u64> {
+    let hunk = hunk_at(hunks, address)?;
+    let within = address - hunk.a_start;
+    (within < hunk.b_length).then_some(hunk.b_start + within)
+}
+
+/// Describes how `address` of the first file lines up with the second.
+pub fn describe(hunks: &[Hunk], address: u64) -> Option<String> {
+    if let Some(hunk) = insert_before(hunks, address) {
+        return Some(format!(
+            "{} byte(s) inserted at {:06x}",
+            hunk.b_length, hunk.b_start
+        ));
+    }
+
+    let hunk = hunk_at(hunks, address)?;
+    Some(match hunk.kind {
+        HunkKind::Equal => format!("matches {:06x}", hunk.b_start + address - hunk.a_start),
+        HunkKind::Change => format!(
+            "changed, {} -> {} byte(s) at {:06x}",
+            hunk.a_length, hunk.b_length, hunk.b_start
+        ),
+        _ => format!("{} byte(s) deleted", hunk.a_length),
+    })
+}
+
+/// The first difference starting after `from` in the first file.
+pub fn next_difference(hunks: &[Hunk], from: u64) -> Option<&Hunk> {
+    hunks
+        .iter()
+        .find(|hunk| hunk.kind != HunkKind::Equal && hunk.a_start > from)
+}
+
+/// The last difference starting before `from` in the first file.
+pub fn previous_difference(hunks: &[Hunk], from: u64) -> Option<&Hunk> {
+    hunks
+        .iter()
+        .rev()
+        .find(|hunk| hunk.kind != HunkKind::Equal && hunk.a_start < from)
+}
+
+/// Describes an alignment in one line for the status bar.
+pub fn summarize(hunks: &[Hunk]) -> String {
+    if hunks.iter().all(|hunk| hunk.kind == HunkKind::Equal) {
+        return "The files are identical.".to_owned();
+    }
+
+    let total = |kind: HunkKind| {
+        let of_kind = hunks.iter().filter(|hunk| hunk.kind == kind);
+        let count = of_kind.clone().count();
+        let a_bytes: u64 = of_kind.clone().map(|hunk| hunk.a_length).sum();
+        let b_bytes: u64 = of_kind.map(|hunk| hunk.b_length).sum();
+        (count, a_bytes, b_bytes)
+    };
+    let (equal, equal_bytes, _) = total(HunkKind::Equal);
+    let (inserted, _, inserted_bytes) = total(HunkKind::Insert);
+    let (deleted, deleted_bytes, _) = total(HunkKind::Delete);
+    let (changed, changed_from, changed_to) = total(HunkKind::Change);
+    format!(
+        "{} matched ({} bytes), {} inserted ({} bytes), {} deleted ({} bytes), {} changed ({} -> {} bytes)",
+        equal, equal_bytes, inserted, inserted_bytes, deleted, deleted_bytes, changed, changed_from, changed_to
+    )
+}
+
+/// Prints the alignment of two files as a report.
+pub fn diff_files(a_name: &str, b_name: &str, all: bool, out: &mut dyn Write) -> io::Result<()> {
+    let a = SedecimFileInfo::new(a_name.to_owned())?;
+    let b = SedecimFileInfo::new(b_name.to_owned())?;
+    write_report(&align_files(&a, &b)?, all, out)
+}
+
+/// Writes one line per hunk and a summary, leaving out matched stretches
+/// unless `all` is set.
+pub fn write_report(hunks: &[Hunk], all: bool, out: &mut dyn Write) -> io::Result<()> {
+    for hunk in hunks {
+        if hunk.kind == HunkKind::Equal && !all {
+            continue;
+        }
+        writeln!(
+            out,
+            "{:<6}  a {:08x} +{:<10}  b {:08x} +{}",
+            hunk.kind.label(),
+            hunk.a_start,
+            hunk.a_length,
+            hunk.b_start,
+            hunk.b_length
+        )?;
+    }
+    writeln!(out, "{}", summarize(hunks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_files::open;
+
+    fn kinds(hunks: &[Hunk]) -> Vec<(HunkKind, u64, u64, u64, u64)> {
+        hunks
+            .iter()
+            .map(|hunk| {
+                (
+                    hunk.kind,
+                    hunk.a_start,
+                    hunk.a_length,
+                    hunk.b_start,
+                    hunk.b_length,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_inserted_deleted_and_changed_bytes() {
+        let hunks = align(b"header-body-footer", b"header-NEW-bodx-footer");
+        assert_eq!(
+            kinds(&hunks),
+            [
+                (HunkKind::Equal, 0, 7, 0, 7),
+                (HunkKind::Insert, 7, 0, 7, 4),
+                (HunkKind::Equal, 7, 3, 11, 3),
+                (HunkKind::Change, 10, 1, 14, 1),
+                (HunkKind::Equal, 11, 7, 15, 7),
+            ]
+        );
+
+        let hunks = align(b"abcdef", b"abef");
+        assert_eq!(
+            kinds(&hunks),
+            [
+                (HunkKind::Equal, 0, 2, 0, 2),
+                (HunkKind::Delete, 2, 2, 2, 0),
+                (HunkKind::Equal, 4, 2, 2, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_data_is_one_match() {
+        let hunks = align(b"same", b"same");
+        assert_eq!(kinds(&hunks), [(HunkKind::Equal, 0, 4, 0, 4)]);
+        assert_eq!(summarize(&hunks), "The files are identical.");
+        assert!(align(b"", b"").is_empty());
+    }
+
+    /// Pseudo random bytes, so blocks do not repeat.
+    fn noise(length: usize, mut seed: u64) -> Vec<u8> {
+        (0..length)
+            .map(|_| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (seed >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aligns_large_files_around_insertions() {
+        let a = noise(300_000, 1);
+        let mut b = a.clone();
+        b.splice(10..10, [1, 2, 3]);
+        b.drain(200_000..200_100);
+        b[250_000] ^= 0xff;
+        let mut b_end = noise(100_000, 2);
+        b.append(&mut b_end);
+        let mut a = a;
+        a.truncate(299_000);
+        a.extend(noise(80_000, 3));
+
+        let hunks = align(&a, &b);
+        let changes: Vec<(HunkKind, u64, u64, u64, u64)> = kinds(&hunks)
+            .into_iter()
+            .filter(|hunk| hunk.0 != HunkKind::Equal)
+            .collect();
+        assert_eq!(changes[0], (HunkKind::Insert, 10, 0, 10, 3));
+        assert_eq!(changes[1], (HunkKind::Delete, 199_997, 100, 200_000, 0));
+        assert_eq!(changes[2], (HunkKind::Change, 250_097, 1, 250_000, 1));
+        assert_eq!(changes.len(), 4);
+
+        // Every byte of both files is covered once, in order.
+        let mut a_position = 0;
+        let mut b_position = 0;
+        for hunk in &hunks {
+            assert_eq!((hunk.a_start, hunk.b_start), (a_position, b_position));
+            a_position += hunk.a_length;
+            b_position += hunk.b_length;
+        }
+        assert_eq!((a_position, b_position), (a.len() as u64, b.len() as u64));
+    }
+
+    #[test]
+    fn aligns_files_read_in_chunks() {
+        let a_data = noise(3 * CHUNK_SIZE, 4);
+        let mut b_data = a_data.clone();
+        b_data.drain(CHUNK_SIZE..CHUNK_SIZE + 50);
+        b_data[2 * CHUNK_SIZE] ^= 0xff;
+        let (a, _a_file) = open(&a_data);
+        let (mut b, _b_file) = open(&b_data);
+        b.insert_bytes(100, &[1, 2, 3]);
+        b_data.splice(100..100, [1, 2, 3]);
+
+        let hunks = align_files(&a, &b).unwrap();
+        assert_eq!(hunks, align(&a_data, &b_data));
+        let changes: Vec<(HunkKind, u64, u64, u64, u64)> = kinds(&hunks)
+            .into_iter()
+            .filter(|hunk| hunk.0 != HunkKind::Equal)
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (HunkKind::Insert, 100, 0, 100, 3),
+                (
+                    HunkKind::Delete,
+                    CHUNK_SIZE as u64,
+                    50,
+                    CHUNK_SIZE as u64 + 3,
+                    0
+                ),
+                (
+                    HunkKind::Change,
+                    2 * CHUNK_SIZE as u64 + 50,
+                    1,
+                    2 * CHUNK_SIZE as u64 + 3,
+                    1
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn maps_offsets_between_files() {
+        let hunks = align(b"header-body", b"header-NEW-body");
+        assert_eq!(counterpart(&hunks, 3), Some(3));
+        assert_eq!(counterpart(&hunks, 8), Some(12));
+        assert_eq!(insert_before(&hunks, 7).map(|hunk| hunk.b_length), Some(4));
+        assert_eq!(insert_before(&hunks, 8), None);
+        assert_eq!(describe(&hunks, 7).unwrap(), "4 byte(s) inserted at 000007");
+        assert_eq!(describe(&hunks, 9).unwrap(), "matches 00000d");
+
+        assert_eq!(next_difference(&hunks, 0).map(|hunk| hunk.a_start), Some(7));
+        assert_eq!(next_difference(&hunks, 7), None);
+        assert_eq!(
+            previous_difference(&hunks, 8).map(|hunk| hunk.a_start),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn writes_a_report() {
+        let hunks = align(b"abcdef", b"abXdef");
+        let mut out = vec![];
+        write_report(&hunks, false, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "change  a 00000002 +1           b 00000002 +1\n\
+             2 matched (5 bytes), 0 inserted (0 bytes), 0 deleted (0 bytes), 1 changed (1 -> 1 bytes)\n"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::io::{self, Write};
+
+use super::sedecim_file_info::SedecimFileInfo;
+
+/// Lines read from the file at a time.
+const CHUNK_LINES: usize = 4096;
+
+/// Layout of the rows printed by the dump mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DumpStyle {
+    /// The rows drawn by the hex view.
+    Sedecim,
+    /// `xxd` output, e.g. `00000000: 7f45 4c46  .ELF`.
+    Xxd,
+    /// `hexdump -C` output, e.g. `00000000  7f 45 4c 46  |.ELF|`.
+    Hexdump,
+}
+
+impl DumpStyle {
+    pub fn parse(text: &str) -> Option<DumpStyle> {
+        match text {
+            "sedecim" => Some(DumpStyle::Sedecim),
+            "xxd" => Some(DumpStyle::Xxd),
+            "hexdump" => Some(DumpStyle::Hexdump),
+            _ => None,
+        }
+    }
+
+    /// Bytes per group when none is given, matching the tool being copied.
+    fn default_group(self) -> usize {
+        match self {
+            DumpStyle::Sedecim => 0,
+            DumpStyle::Xxd => 2,
+            DumpStyle::Hexdump => 8,
+        }
+    }
+}
+
+pub struct DumpOptions {
+    pub style: DumpStyle,
+    pub offset: u64,
+    pub length: Option<u64>,
+    pub cols: usize,
+    /// Bytes per group, 0 for no grouping. `None` uses the style default.
+    pub group: Option<usize>,
+}
+
+impl DumpOptions {
+    pub fn new() -> DumpOptions {
+        DumpOptions {
+            style: DumpStyle::Sedecim,
+            offset: 0,
+            length: None,
+            cols: 16,
+            group: None,
+        }
+    }
+}
+
+/// Character shown for a byte in the text pane.
+pub fn display_char(value: u8) -> char {
+    if (0x20..0x7f).contains(&value) {
+        value as char
+    } else {
+        '.'
+    }
+}
+
+/// Number of hex digits used to show addresses in a file of `file_size`
+/// bytes.
+pub fn address_width(file_size: u64) -> usize {
+    let digits = (64 - file_size.leading_zeros() as usize).div_ceil(4);
+    digits.max(6)
+}
+
+/// Prints a file as rows of address, hex and text.
+pub fn dump_file(file_name: &str, options: &DumpOptions, out: &mut dyn Write) -> io::Result<()> {
+    let info = SedecimFileInfo::new(file_name.to_owned())?;
+    dump(&info, options, out)
+}
+
+pub fn dump(info: &SedecimFileInfo, options: &DumpOptions, out: &mut dyn Write) -> io::Result<()> {
+    let cols = options.cols.max(1);
+    let group = options.group.unwrap_or(options.style.default_group());
+    let start = options.offset.min(info.file_size);
+    let end = match options.length {
+        Some(length) => start.saturating_add(length).min(info.file_size),
+        None => info.file_size,
+    };
+    let width = address_width(info.file_size);
+
+    let mut buffer = vec![0; cols * CHUNK_LINES];
+    let mut previous: Option<Vec<u8>> = None;
+    let mut squeezing = false;
+    let mut address = start;
+    while address < end {
+        let wanted = ((end - address) as usize).min(buffer.len());
+        let read = info.read_at(address, &mut buffer[..wanted])?;
+        if read == 0 {
+            break;
+        }
+
+        for line in buffer[..read].chunks(cols) {
+            // hexdump -C prints a single "*" for a run of repeated lines.
+            if options.style == DumpStyle::Hexdump && line.len() == cols {
+                if previous.as_deref() == Some(line) {
+                    if !squeezing {
+                        writeln!(out, "*")?;
+                        squeezing = true;
+                    }
+                    address += cols as u64;
+                    continue;
+                }
+                squeezing = false;
+                previous = Some(line.to_vec());
+            }
+
+            let text = match options.style {
+                DumpStyle::Sedecim => sedecim_line(address, line, cols, group, width),
+                DumpStyle::Xxd => xxd_line(address, line, cols, group),
+                DumpStyle::Hexdump => hexdump_line(address, line, cols, group),
+            };
+            writeln!(out, "{}", text)?;
+            address += line.len() as u64;
+        }
+    }
+
+    if options.style == DumpStyle::Hexdump && end > start {
+        writeln!(out, "{:08x}", end)?;
+    }
+
+    Ok(())
+}
+
+fn is_group_end(index: usize, group: usize) -> bool {
+    group > 0 && (index + 1).is_multiple_of(group)
+}
+
+fn sedecim_line(address: u64, line: &[u8], cols: usize, group: usize, width: usize) -> String {
+    let mut text = format!(" {:0width$x}  ", address, width = width);
+    for index in 0..cols {
+        match line.get(index) {
+            Some(value) => text.push_str(&format!("{:02x} ", value)),
+            None => text.push_str("   "),
+        }
+        if is_group_end(index, group) && index + 1 < cols {
+            text.push(' ');
+        }
+    }
+
+    text.push_str(" | ");
+    for value in line {
+        text.push(display_char(*value));
+        text.push(' ');
+    }
+
+    text.trim_end().to_owned()
+}
+
+fn xxd_line(address: u64, line: &[u8], cols: usize, group: usize) -> String {
+    let mut text = format!("{:08x}: ", address);
+    for index in 0..cols {
+        match line.get(index) {
+            Some(value) => text.push_str(&format!("{:02x}", value)),
+            None => text.push_str("  "),
+        }
+        if is_group_end(index, group) && index + 1 < cols {
+            text.push(' ');
+        }
+    }
+
+    text.push_str("  ");
+    text.extend(line.iter().map(|value| display_char(*value)));
+    text
+}
+
+fn hexdump_line(address: u64, line: &[u8], cols: usize, group: usize) -> String {
+    let mut text = format!("{:08x}  ", address);
+    for index in 0..cols {
+        match line.get(index) {
+            Some(value) => text.push_str(&format!("{:02x} ", value)),
+            None => text.push_str("   "),
+        }
+        if is_group_end(index, group) && index + 1 < cols {
+            text.push(' ');
+        }
+    }
+
+    text.push_str(" |");
+    text.extend(line.iter().map(|value| display_char(*value)));
+    text.push('|');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_files::temp_file;
+    use tempfile::NamedTempFile;
+
+    fn dump_text(file: &NamedTempFile, options: &DumpOptions) -> String {
+        let mut out = vec![];
+        dump_file(&file.path().to_string_lossy(), options, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn xxd_style() {
+        let file = temp_file(b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00abc");
+        let mut options = DumpOptions::new();
+        options.style = DumpStyle::Xxd;
+
+        assert_eq!(
+            dump_text(&file, &options),
+            "00000000: 7f45 4c46 0201 0100 0000 0000 0000 0000  .ELF............\n\
+             00000010: 6162 63                                  abc\n"
+        );
+    }
+
+    #[test]
+    fn hexdump_style_squeezes_repeats() {
+        let mut contents = vec![0; 48];
+        contents.extend_from_slice(b"hello\n");
+        let file = temp_file(&contents);
+        let mut options = DumpOptions::new();
+        options.style = DumpStyle::Hexdump;
+
+        assert_eq!(
+            dump_text(&file, &options),
+            "00000000  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|\n\
+             *\n\
+             00000030  68 65 6c 6c 6f 0a                                 |hello.|\n\
+             00000036\n"
+        );
+    }
+
+    #[test]
+    fn sedecim_style_with_range() {
+        let file = temp_file(b"0123456789abcdef");
+        let mut options = DumpOptions::new();
+        options.offset = 2;
+        options.length = Some(5);
+        options.cols = 4;
+
+        assert_eq!(
+            dump_text(&file, &options),
+            " 000002  32 33 34 35  | 2 3 4 5\n 000006  36           | 6\n"
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use chrono::{DateTime, Local};
+
+use super::piece_table::PieceEdit;
+
+/// Most bytes a single undo step should change, as each one is journaled
+/// with its old and new value.
+pub const MAX_GROUP_CHANGES: u64 = 16 * 1024 * 1024;
+
+/// One byte changed by an edit. Inserted bytes have no old value and deleted
+/// bytes have no new value.
+#[derive(Clone)]
+pub struct ByteChange {
+    pub offset: u64,
+    pub old_value: Option<u8>,
+    pub new_value: Option<u8>,
+    pub timestamp: DateTime<Local>,
+}
+
+/// Changes undone or redone as a single step, together with the changes to
+/// the piece list that made them.
+pub struct EditGroup {
+    pub changes: Vec<ByteChange>,
+    pub pieces: Vec<PieceEdit>,
+}
+
+/// History of byte changes, kept in groups that are undone or redone as a
+/// single step.
+pub struct EditJournal {
+    undo_stack: Vec<EditGroup>,
+    redo_stack: Vec<EditGroup>,
+    group_depth: usize,
+    /// False when edits are saved straight away and never undone.
+    enabled: bool,
+}
+
+impl EditJournal {
+    pub fn new() -> EditJournal {
+        let undo_stack = vec![];
+        let redo_stack = vec![];
+        let group_depth = 0;
+        let enabled = true;
+
+        EditJournal {
+            undo_stack,
+            redo_stack,
+            group_depth,
+            enabled,
+        }
+    }
+
+    /// Stops recording changes, for edits that are saved straight away,
+    /// such as a patch applied from the command line.
+    pub fn disable(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.enabled = false;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Starts collecting changes into one undo step. Groups may nest; only
+    /// the outermost `end_group` closes the step.
+    pub fn begin_group(&mut self) {
+        if self.group_depth == 0 && self.enabled {
+            self.undo_stack.push(EditGroup {
+                changes: vec![],
+                pieces: vec![],
+            });
+        }
+        self.group_depth += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        if self.group_depth == 0 {
+            return;
+        }
+
+        self.group_depth -= 1;
+        if self.group_depth == 0
+            && self.enabled
+            && self
+                .undo_stack
+                .last()
+                .is_some_and(|group| group.changes.is_empty())
+        {
+            self.undo_stack.pop();
+        }
+    }
+
+    /// Adds a change to the open group. Changes made outside of a group are
+    /// not recorded.
+    pub fn record(&mut self, offset: u64, old_value: Option<u8>, new_value: Option<u8>) {
+        if self.group_depth == 0 || !self.enabled {
+            return;
+        }
+
+        let change = ByteChange {
+            offset,
+            old_value,
+            new_value,
+            timestamp: Local::now(),
+        };
+
+        self.redo_stack.clear();
+        if let Some(group) = self.undo_stack.last_mut() {
+            group.changes.push(change);
+        }
+    }
+
+    /// Adds changes to the piece list to the open group, to be reverted
+    /// with it.
+    pub fn record_pieces(&mut self, edits: Vec<PieceEdit>) {
+        if self.group_depth == 0 || !self.enabled {
+            return;
+        }
+        if let Some(group) = self.undo_stack.last_mut() {
+            group.pieces.extend(edits);
+        }
+    }
+
+    pub fn pop_undo(&mut self) -> Option<EditGroup> {
+        if self.group_depth > 0 {
+            return None;
+        }
+        self.undo_stack.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<EditGroup> {
+        if self.group_depth > 0 {
+            return None;
+        }
+        self.redo_stack.pop()
+    }
+
+    pub fn push_undo(&mut self, group: EditGroup) -> &[ByteChange] {
+        self.undo_stack.push(group);
+        &self.undo_stack.last().unwrap().changes
+    }
+
+    pub fn push_redo(&mut self, group: EditGroup) -> &[ByteChange] {
+        self.redo_stack.push(group);
+        &self.redo_stack.last().unwrap().changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_group(journal: &mut EditJournal, offset: u64) {
+        journal.begin_group();
+        journal.record(offset, Some(1), Some(2));
+        journal.end_group();
+    }
+
+    #[test]
+    fn each_group_is_one_step() {
+        let mut journal = EditJournal::new();
+        record_group(&mut journal, 0);
+        record_group(&mut journal, 1);
+
+        assert_eq!(journal.pop_undo().unwrap().changes[0].offset, 1);
+        assert_eq!(journal.pop_undo().unwrap().changes[0].offset, 0);
+        assert!(journal.pop_undo().is_none());
+    }
+
+    #[test]
+    fn nested_groups_make_one_step() {
+        let mut journal = EditJournal::new();
+        journal.begin_group();
+        journal.record(0, Some(1), Some(2));
+        journal.begin_group();
+        journal.record(1, None, Some(4));
+        journal.end_group();
+        assert!(journal.pop_undo().is_none());
+        journal.end_group();
+
+        assert_eq!(journal.pop_undo().unwrap().changes.len(), 2);
+    }
+
+    #[test]
+    fn empty_group_is_dropped() {
+        let mut journal = EditJournal::new();
+        journal.begin_group();
+        journal.end_group();
+
+        assert!(journal.pop_undo().is_none());
+    }
+
+    #[test]
+    fn disabled_journal_records_nothing() {
+        let mut journal = EditJournal::new();
+        journal.disable();
+        record_group(&mut journal, 0);
+
+        assert!(!journal.is_enabled());
+        assert!(journal.pop_undo().is_none());
+    }
+
+    #[test]
+    fn record_clears_redo() {
+        let mut journal = EditJournal::new();
+        record_group(&mut journal, 0);
+        let group = journal.pop_undo().unwrap();
+        journal.push_redo(group);
+        record_group(&mut journal, 5);
+
+        assert!(journal.pop_redo().is_none());
+    }
+}
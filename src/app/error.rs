@@ -0,0 +1,66 @@
+use std::io;
+
+use thiserror::Error;
+
+/// Problems reading or writing the file being viewed, or driving the
+/// terminal.
+#[derive(Debug, Error)]
+pub enum SedecimError {
+    #[error("{path}: {source}")]
+    Open {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("{path}: read failed at {offset:#x}: {source}")]
+    Read {
+        path: String,
+        offset: u64,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("{path}: file ends before {offset:#x}, it may have changed on disk")]
+    ShortRead { path: String, offset: u64 },
+
+    #[error("{path}: {source}")]
+    Write {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("{path}: {size} bytes is too large to load, the limit is {limit} MiB")]
+    TooLarge { path: String, size: u64, limit: u64 },
+
+    #[error("terminal: {0}")]
+    Terminal(#[source] io::Error),
+}
+
+impl SedecimError {
+    /// Wraps an error reading `path` at `offset`, telling a file that got
+    /// shorter apart from other failures.
+    pub fn read(path: &str, offset: u64, source: io::Error) -> SedecimError {
+        if source.kind() == io::ErrorKind::UnexpectedEof {
+            SedecimError::ShortRead {
+                path: path.to_owned(),
+                offset,
+            }
+        } else {
+            SedecimError::Read {
+                path: path.to_owned(),
+                offset,
+                source,
+            }
+        }
+    }
+}
+
+/// Lets code that streams through the file, such as search and dump, keep
+/// returning `io::Result`.
+impl From<SedecimError> for io::Error {
+    fn from(err: SedecimError) -> io::Error {
+        io::Error::other(err)
+    }
+}
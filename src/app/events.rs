@@ -1,13 +1,18 @@
 use std::{
     sync::{mpsc, mpsc::*},
     thread,
-    time::{Duration, Instant},
+    time::Duration,
 };
 
-use crossterm::event::{self, Event as CEvent, KeyEvent};
+use crossterm::event::{self, Event as CEvent, KeyEvent, MouseEvent};
 
 pub enum Event<I> {
     Input(I),
+    Mouse(MouseEvent),
+    /// The terminal was resized to the given columns and rows.
+    Resize(u16, u16),
+    /// Nothing happened for a while, so a live view can be redrawn, or no
+    /// event could be read as the input thread has stopped.
     Tick,
 }
 
@@ -18,25 +23,27 @@ pub struct SecdecimEvents {
 impl SecdecimEvents {
     pub fn new() -> SecdecimEvents {
         let (tx, rx) = mpsc::channel();
-        let tick_rate = Duration::from_millis(200);
         thread::spawn(move || -> ! {
-            let last_tick = Instant::now();
             loop {
-                let timeout = tick_rate
-                    .checked_sub(last_tick.elapsed())
-                    .unwrap_or_else(|| Duration::from_secs(0));
-
-                if event::poll(timeout).expect("Polling event has failed!") {
-                    if let CEvent::Key(key) = event::read().expect("Unable to read the event!") {
-                        tx.send(Event::Input(key)).expect("Unable to send the event!");
-                    }
-                }                
+                let event = match event::read().expect("Unable to read the event!") {
+                    CEvent::Key(key) => Some(Event::Input(key)),
+                    CEvent::Mouse(mouse) => Some(Event::Mouse(mouse)),
+                    CEvent::Resize(columns, rows) => Some(Event::Resize(columns, rows)),
+                    _ => None,
+                };
+                if let Some(event) = event {
+                    tx.send(event).expect("Unable to send the event!");
+                }
             }
         });
         SecdecimEvents { rx }
     }
 
-    pub fn next(&self) -> Event<KeyEvent> {
-        return self.rx.recv().unwrap_or(Event::Tick);
+    /// Waits for the next event, or up to `tick` when it is set.
+    pub fn next(&self, tick: Option<Duration>) -> Event<KeyEvent> {
+        match tick {
+            Some(tick) => self.rx.recv_timeout(tick).unwrap_or(Event::Tick),
+            None => self.rx.recv().unwrap_or(Event::Tick),
+        }
     }
 }
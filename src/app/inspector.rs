@@ -0,0 +1,202 @@
+use std::fmt::{Display, LowerExp};
+
+use chrono::DateTime;
+
+/// Number of bytes at the cursor the inspector needs to decode every type.
+pub const INSPECT_SIZE: usize = 16;
+
+/// One decoded value. Types where byte order does not apply have no big
+/// endian value.
+pub struct Interpretation {
+    pub label: &'static str,
+    pub little: String,
+    pub big: Option<String>,
+}
+
+/// Decodes the bytes at the cursor as every type the inspector shows.
+/// Values that need more bytes than are left in the file are shown as "-".
+pub fn interpret(data: &[u8]) -> Vec<Interpretation> {
+    vec![
+        single("binary", data.first().map(|value| format!("{:08b}", value))),
+        single("i8", data.first().map(|value| (*value as i8).to_string())),
+        single("u8", data.first().map(|value| value.to_string())),
+        both(data, "i16", |b: [u8; 2]| i16::from_le_bytes(b).to_string()),
+        both(data, "u16", |b: [u8; 2]| u16::from_le_bytes(b).to_string()),
+        both(data, "i32", |b: [u8; 4]| i32::from_le_bytes(b).to_string()),
+        both(data, "u32", |b: [u8; 4]| u32::from_le_bytes(b).to_string()),
+        both(data, "i64", |b: [u8; 8]| i64::from_le_bytes(b).to_string()),
+        both(data, "u64", |b: [u8; 8]| u64::from_le_bytes(b).to_string()),
+        both(data, "f32", |b: [u8; 4]| {
+            format_float(f32::from_le_bytes(b))
+        }),
+        both(data, "f64", |b: [u8; 8]| {
+            format_float(f64::from_le_bytes(b))
+        }),
+        single(
+            "uleb128",
+            read_leb128(data, false).map(|(value, size)| format!("{} ({}b)", value, size)),
+        ),
+        single(
+            "sleb128",
+            read_leb128(data, true).map(|(value, size)| format!("{} ({}b)", value as i64, size)),
+        ),
+        both(data, "time32", |b: [u8; 4]| {
+            format_time(u32::from_le_bytes(b) as i64)
+        }),
+        both(data, "time64", |b: [u8; 8]| {
+            format_time(i64::from_le_bytes(b))
+        }),
+        Interpretation {
+            label: "guid",
+            little: format_guid(data, true),
+            big: Some(format_guid(data, false)),
+        },
+    ]
+}
+
+fn single(label: &'static str, value: Option<String>) -> Interpretation {
+    Interpretation {
+        label,
+        little: value.unwrap_or_else(|| "-".to_owned()),
+        big: None,
+    }
+}
+
+/// Decodes the first `N` bytes with `decode`, which reads little endian, once
+/// as they are and once reversed to get the big endian value.
+fn both<const N: usize>(
+    data: &[u8],
+    label: &'static str,
+    decode: impl Fn([u8; N]) -> String,
+) -> Interpretation {
+    let Some(bytes) = data
+        .get(..N)
+        .and_then(|bytes| <[u8; N]>::try_from(bytes).ok())
+    else {
+        return Interpretation {
+            label,
+            little: "-".to_owned(),
+            big: Some("-".to_owned()),
+        };
+    };
+
+    let mut reversed = bytes;
+    reversed.reverse();
+    Interpretation {
+        label,
+        little: decode(bytes),
+        big: Some(decode(reversed)),
+    }
+}
+
+/// Shows very large and very small values in exponent form so they fit in
+/// the panel.
+fn format_float<T: Into<f64> + Display + LowerExp + Copy>(value: T) -> String {
+    let wide: f64 = value.into();
+    if wide == 0.0 || !wide.is_finite() || (1e-4..1e15).contains(&wide.abs()) {
+        format!("{}", value)
+    } else {
+        format!("{:e}", value)
+    }
+}
+
+fn format_time(seconds: i64) -> String {
+    match DateTime::from_timestamp(seconds, 0) {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => "invalid".to_owned(),
+    }
+}
+
+/// Reads a LEB128 value, returning it with the number of bytes it used, or
+/// `None` when it does not end within `data` or overflows 64 bits.
+fn read_leb128(data: &[u8], signed: bool) -> Option<(u64, usize)> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+
+    for (index, byte) in data.iter().enumerate() {
+        if shift >= 64 {
+            return None;
+        }
+
+        value |= ((byte & 0x7f) as u64) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            if signed && shift < 64 && byte & 0x40 != 0 {
+                value |= u64::MAX << shift;
+            }
+            return Some((value, index + 1));
+        }
+    }
+
+    None
+}
+
+/// Formats 16 bytes as a GUID. The little endian form stores the first three
+/// fields little endian, as Windows does.
+fn format_guid(data: &[u8], little: bool) -> String {
+    let Some(bytes) = data.get(..16) else {
+        return "-".to_owned();
+    };
+
+    let mut order: Vec<u8> = bytes.to_vec();
+    if little {
+        order[0..4].reverse();
+        order[4..6].reverse();
+        order[6..8].reverse();
+    }
+
+    let hex: Vec<String> = order.iter().map(|value| format!("{:02x}", value)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        hex[0..4].concat(),
+        hex[4..6].concat(),
+        hex[6..8].concat(),
+        hex[8..10].concat(),
+        hex[10..16].concat()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find<'a>(rows: &'a [Interpretation], label: &str) -> &'a Interpretation {
+        rows.iter().find(|row| row.label == label).unwrap()
+    }
+
+    #[test]
+    fn decodes_both_byte_orders() {
+        let rows = interpret(&[0xfe, 0xff, 0x00, 0x00]);
+
+        assert_eq!(find(&rows, "i8").little, "-2");
+        assert_eq!(find(&rows, "i16").little, "-2");
+        assert_eq!(find(&rows, "u16").big.as_deref(), Some("65279"));
+        assert_eq!(find(&rows, "u32").little, "65534");
+        assert_eq!(find(&rows, "u64").little, "-");
+        assert_eq!(find(&rows, "binary").little, "11111110");
+    }
+
+    #[test]
+    fn decodes_leb128() {
+        assert_eq!(read_leb128(&[0xe5, 0x8e, 0x26], false), Some((624485, 3)));
+        assert_eq!(
+            read_leb128(&[0xc0, 0xbb, 0x78], true).map(|(value, _)| value as i64),
+            Some(-123456)
+        );
+        assert_eq!(read_leb128(&[0x80, 0x80], false), None);
+    }
+
+    #[test]
+    fn decodes_time_and_guid() {
+        let rows = interpret(&[
+            0x33, 0x22, 0x11, 0x00, 0x55, 0x44, 0x77, 0x66, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
+            0xee, 0xff,
+        ]);
+
+        assert_eq!(
+            find(&rows, "guid").little,
+            "00112233-4455-6677-8899-aabbccddeeff"
+        );
+        assert_eq!(find(&rows, "time32").little, "1970-01-13 23:54:27");
+    }
+}
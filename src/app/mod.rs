@@ -1,56 +1,252 @@
+mod byte_source;
+mod checksum;
+mod compare;
+pub mod diff;
+pub mod dump;
+mod edit_journal;
+mod error;
 mod events;
+mod inspector;
+mod page_cache;
+mod partitions;
+pub mod patch;
+mod piece_table;
+mod search;
 mod sedecim_file_info;
 mod sedecim_file_page;
+#[cfg(test)]
+mod test_files;
 mod ui;
+pub mod undump;
+
+pub use byte_source::BackendKind;
+pub use error::SedecimError;
+pub use ui::Theme;
 
 use std::io::{self, Stdout};
-use tui::{backend::CrosstermBackend, Terminal};
+use std::panic;
+use std::time::Duration;
+use tui::{backend::CrosstermBackend, layout::Rect, Terminal};
 
 use crossterm::{
     cursor::{EnableBlinking, MoveTo, Show as ShowCursor},
-    event::{DisableMouseCapture, EnableMouseCapture, KeyCode, KeyModifiers},
+    event::{
+        DisableMouseCapture, EnableMouseCapture, KeyCode, KeyEvent, KeyModifiers, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 
+/// Lines scrolled for each step of the mouse wheel.
+const WHEEL_LINES: u64 = 3;
+
+/// Largest selection that can be copied to the clipboard.
+const MAX_CLIPBOARD: u64 = 16 * 1024 * 1024;
+
+/// Largest selection that can be filled, as every byte is journaled.
+const MAX_FILL: u64 = edit_journal::MAX_GROUP_CHANGES;
+
+/// How often a view of live memory is read again while no keys are pressed.
+const LIVE_REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+const READ_ONLY_STATUS: &str = "Read-only: editing is disabled";
+
 pub enum AppMode {
     Standard,
+    Edit,
+    Insert,
+    Visual,
     Jump,
+    Search,
+    Results,
+    Partitions,
+    Regions,
+    SaveAs,
+    ConfirmQuit,
+    Fill,
+    Export,
+    SavePatch,
+    ApplyPatch,
     Help,
 }
 
+/// How many bytes are shown on each line of the hex view.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Columns {
+    /// As many as fit in the width of the terminal.
+    Auto,
+    Fixed(u64),
+}
+
+impl Columns {
+    pub fn next(self) -> Columns {
+        match self {
+            Columns::Auto => Columns::Fixed(8),
+            Columns::Fixed(8) => Columns::Fixed(16),
+            Columns::Fixed(16) => Columns::Fixed(32),
+            Columns::Fixed(_) => Columns::Auto,
+        }
+    }
+}
+
+/// How the viewer is opened, as given on the command line.
+pub struct Options {
+    pub file_name: String,
+    /// Views the memory of this process instead of a file.
+    pub pid: Option<u32>,
+    /// A second file shown beside the first and compared with it.
+    pub compare: Option<String>,
+    /// Offset the cursor starts on.
+    pub offset: u64,
+    /// Disables every command that changes the data.
+    pub read_only: bool,
+    pub columns: Columns,
+    pub theme: Theme,
+    pub backend: BackendKind,
+}
+
 pub struct App {
     events: events::SecdecimEvents,
     pub file_info: sedecim_file_info::SedecimFileInfo,
+    pub compare: Option<compare::Compare>,
     pub selected_line: i32,
     pub selected_value: i32,
     pub mode: AppMode,
     pub jump_value: String,
+    /// The jump prompt takes a decimal sector number rather than a hex
+    /// address.
+    pub jump_sector: bool,
     pub error: String,
+    pub edit_low_nibble: bool,
+    pub save_path: String,
+    pub status: String,
+    pub search_value: String,
+    pub search_kind: search::SearchKind,
+    pub search_ignore_case: bool,
+    pub search_pattern: Option<search::SearchPattern>,
+    pub search_results: Vec<u64>,
+    pub selected_result: usize,
+    pub partition_table: Option<partitions::PartitionTable>,
+    pub selected_partition: usize,
+    pub selected_region: usize,
+    pub columns: Columns,
+    pub selection_anchor: Option<u64>,
+    pub drag_start: Option<u64>,
+    pub hex_area: Rect,
+    pub fill_value: String,
+    pub export_path: String,
+    pub patch_path: String,
+    pub clipboard: Vec<u8>,
+    pub show_inspector: bool,
+    pub read_only: bool,
+    pub theme: Theme,
 }
 
 impl App {
-    pub fn new(args: Vec<String>) -> Self {
+    pub fn new(options: Options) -> Result<Self, SedecimError> {
+        let file_info = match options.pid {
+            Some(pid) => sedecim_file_info::SedecimFileInfo::open_process(pid)?,
+            None => sedecim_file_info::SedecimFileInfo::open(options.file_name, options.backend)?,
+        };
+        let compare = match options.compare {
+            Some(file_name) => Some(compare::Compare::new(
+                sedecim_file_info::SedecimFileInfo::open(file_name, options.backend)?,
+            )),
+            None => None,
+        };
         let events = events::SecdecimEvents::new();
-        let file_info = sedecim_file_info::SedecimFileInfo::new(String::from(&args[1]));
         let selected_line = 0;
         let selected_value = 0;
         let mode = AppMode::Standard;
         let jump_value: String = String::default();
+        let jump_sector = false;
         let error: String = String::default();
-        Self {
+        let edit_low_nibble = false;
+        let save_path: String = String::default();
+        let status: String = String::default();
+        let search_value: String = String::default();
+        let search_kind = search::SearchKind::Hex;
+        let search_ignore_case = false;
+        let search_pattern = None;
+        let search_results = vec![];
+        let selected_result = 0;
+        let partition_table = None;
+        let selected_partition = 0;
+        let selected_region = 0;
+        let columns = options.columns;
+        let selection_anchor = None;
+        let drag_start = None;
+        let hex_area = Rect::default();
+        let fill_value: String = String::default();
+        let export_path: String = String::default();
+        let patch_path: String = String::default();
+        let clipboard = vec![];
+        let show_inspector = true;
+        let read_only = options.read_only;
+        let theme = options.theme;
+        let mut app = Self {
             events,
             file_info,
+            compare,
             selected_line,
             selected_value,
             mode,
             jump_value,
+            jump_sector,
             error,
+            edit_low_nibble,
+            save_path,
+            status,
+            search_value,
+            search_kind,
+            search_ignore_case,
+            search_pattern,
+            search_results,
+            selected_result,
+            partition_table,
+            selected_partition,
+            selected_region,
+            columns,
+            selection_anchor,
+            drag_start,
+            hex_area,
+            fill_value,
+            export_path,
+            patch_path,
+            clipboard,
+            show_inspector,
+            read_only,
+            theme,
+        };
+        // Process memory starts with a gap, so begin on the first region.
+        let file_size = app.file_info.file_size;
+        let offset = match app
+            .file_info
+            .mapped_ranges(options.offset, file_size)
+            .first()
+        {
+            Some((start, _)) => *start,
+            None => options.offset,
+        };
+        app.move_cursor_to(offset);
+        // Comparing reads both files in full, so it waits until asked for.
+        if app.compare.is_some() {
+            app.status = "Press D to summarize the differences, A to align.".to_owned();
         }
+        Ok(app)
     }
 
-    fn init(&mut self) -> Terminal<CrosstermBackend<Stdout>> {
-        self.file_info.set_address(0);
+    fn init(&mut self) -> Result<Terminal<CrosstermBackend<Stdout>>, SedecimError> {
+        self.file_info.set_address(self.file_info.file_offset);
+
+        // Give the terminal back before a panic message is printed, so it is
+        // readable and the shell is usable afterwards.
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            default_hook(info);
+        }));
 
         // setup terminal
         let _ = enable_raw_mode();
@@ -65,185 +261,1390 @@ impl App {
         );
 
         let backend = CrosstermBackend::new(stdout);
-        let mut terminal =
-            Terminal::new(backend).expect("Error creating a new Terminal in App Init");
+        let mut terminal = Terminal::new(backend).map_err(SedecimError::Terminal)?;
         let _ = terminal.clear();
-        terminal
-    }
-
-    pub fn run(&mut self) {
-        let mut terminal = self.init();
-        match self.runner(&mut terminal) {
-            _ => {
-                let _ = disable_raw_mode();
-                let _ = execute!(
-                    terminal.backend_mut(),
-                    LeaveAlternateScreen,
-                    DisableMouseCapture
-                );
-                terminal.show_cursor().expect("Errors");
-                let _ = terminal.clear();
+        Ok(terminal)
+    }
 
-                println!("\n\n");
-                println!("                _              _            ");
-                println!(" ___   ___   __| |  ___   ___ (_) _ __ ___  ");
-                println!("/ __| / _ \\ / _` | / _ \\ / __|| || '_ ` _ \\ ");
-                println!("\\__ \\|  __/| (_| ||  __/| (__ | || | | | | |");
-                println!("|___/ \\___| \\__,_| \\___| \\___||_||_| |_| |_|");
+    pub fn run(&mut self) -> Result<(), SedecimError> {
+        let result = self
+            .init()
+            .and_then(|mut terminal| self.runner(&mut terminal));
+        restore_terminal();
+        result?;
 
-                println!("\n\nThank you for using sedecim!\n\n");
-            }
-        }
+        println!("\n\n");
+        println!("                _              _            ");
+        println!(" ___   ___   __| |  ___   ___ (_) _ __ ___  ");
+        println!("/ __| / _ \\ / _` | / _ \\ / __|| || '_ ` _ \\ ");
+        println!("\\__ \\|  __/| (_| ||  __/| (__ | || | | | | |");
+        println!("|___/ \\___| \\__,_| \\___| \\___||_||_| |_| |_|");
+
+        println!("\n\nThank you for using sedecim!\n\n");
+        Ok(())
     }
 
     fn runner(
         &mut self,
         terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), SedecimError> {
         loop {
-            let _ = ui::draw_ui(self, terminal);
+            self.file_info.refresh();
+            match ui::draw_ui(self, terminal) {
+                Err(SedecimError::Terminal(err)) => return Err(SedecimError::Terminal(err)),
+                // Show a failed read and draw again so the message appears
+                // right away. The bytes that could not be read show as "??".
+                Err(err) if self.status != err.to_string() => {
+                    self.status = err.to_string();
+                    continue;
+                }
+                _ => {}
+            }
 
-            if self.handle_input() {
-                break;
+            // Live memory is redrawn every so often, not only on input.
+            let tick = self.file_info.is_live().then_some(LIVE_REFRESH_INTERVAL);
+            match self.events.next(tick) {
+                events::Event::Input(event) => {
+                    if self.handle_input(event) {
+                        break;
+                    }
+                }
+                // Resize the buffers right away so the next frame is laid
+                // out for the new size, which keeps the cursor in view.
+                events::Event::Resize(columns, rows) => {
+                    terminal
+                        .resize(Rect::new(0, 0, columns, rows))
+                        .map_err(SedecimError::Terminal)?;
+                }
+                events::Event::Mouse(event) => self.handle_mouse(event),
+                // The next draw reads the live data again.
+                events::Event::Tick => {}
             }
         }
 
         Ok(())
     }
 
-    fn handle_input(&mut self) -> bool {
+    fn handle_input(&mut self, event: KeyEvent) -> bool {
         match self.mode {
-            AppMode::Standard => self.handle_input_standard(),
-            AppMode::Jump => self.handle_input_jump(),
-            AppMode::Help => self.handle_input_help(),
+            AppMode::Standard | AppMode::Edit | AppMode::Insert | AppMode::Visual => {
+                self.handle_input_standard(event)
+            }
+            AppMode::Jump => self.handle_input_jump(event),
+            AppMode::Search => self.handle_input_search(event),
+            AppMode::Results => self.handle_input_results(event),
+            AppMode::Partitions => self.handle_input_partitions(event),
+            AppMode::Regions => self.handle_input_regions(event),
+            AppMode::SaveAs => self.handle_input_save_as(event),
+            AppMode::ConfirmQuit => self.handle_input_confirm_quit(event),
+            AppMode::Fill => self.handle_input_fill(event),
+            AppMode::Export => self.handle_input_export(event),
+            AppMode::SavePatch => self.handle_input_save_patch(event),
+            AppMode::ApplyPatch => self.handle_input_apply_patch(event),
+            AppMode::Help => self.handle_input_help(event),
         }
     }
 
-    fn handle_input_standard(&mut self) -> bool {
-        match self.events.next() {
-            events::Event::Input(event) => match event.code {
-                KeyCode::Char('g') => match event.modifiers {
-                    KeyModifiers::CONTROL => {
-                        self.mode = AppMode::Jump;
-                        self.jump_value = String::default();
-                    }
-                    _ => {}
-                },
+    pub fn cursor_offset(&self) -> u64 {
+        self.file_info.file_offset
+            + (self.selected_line as u64) * self.file_info.line_size
+            + (self.selected_value as u64)
+    }
 
-                KeyCode::Char('h') => {
-                    self.mode = AppMode::Help;
-                },
+    /// First and last offset of the selected range, if there is one.
+    pub fn selection(&self) -> Option<(u64, u64)> {
+        let anchor = self.selection_anchor?;
+        let last = self.file_info.file_size.checked_sub(1)?;
+        let cursor = self.cursor_offset();
+        Some((anchor.min(cursor).min(last), anchor.max(cursor).min(last)))
+    }
 
-                KeyCode::Char('q') => {
-                    return true;
+    /// Applies the bytes per line and number of lines worked out when
+    /// drawing, keeping the cursor on the same byte.
+    pub fn set_layout(&mut self, line_size: u64, visible_lines: u64) {
+        if line_size == self.file_info.line_size && visible_lines == self.file_info.visible_lines {
+            return;
+        }
+
+        let cursor = self.cursor_offset();
+        self.file_info.set_layout(line_size, visible_lines);
+        self.move_cursor_to(cursor);
+    }
+
+    fn handle_input_standard(&mut self, event: KeyEvent) -> bool {
+        self.status = "".to_owned();
+        if matches!(self.mode, AppMode::Visual) && self.handle_key_visual(event) {
+            return false;
+        }
+
+        // Chords such as ctrl+f are commands, not nibbles.
+        let plain = event.modifiers.difference(KeyModifiers::SHIFT).is_empty();
+        if let KeyCode::Char(chr) = event.code {
+            if matches!(self.mode, AppMode::Edit | AppMode::Insert)
+                && plain
+                && chr.is_ascii_hexdigit()
+            {
+                self.edit_nibble(chr);
+                return false;
+            }
+        }
+
+        self.finish_nibble_edit();
+        match event.code {
+            KeyCode::Char('g') if event.modifiers == KeyModifiers::CONTROL => {
+                self.mode = AppMode::Jump;
+                self.jump_value = String::default();
+            }
+
+            KeyCode::Char('h') => {
+                self.mode = AppMode::Help;
+            }
+
+            KeyCode::Char('/') => {
+                self.mode = AppMode::Search;
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Char('f') if event.modifiers == KeyModifiers::CONTROL => {
+                self.mode = AppMode::Search;
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Char('n') => {
+                self.find_pattern(true, self.cursor_offset() + 1);
+            }
+
+            KeyCode::Char('N') => {
+                self.find_pattern(false, self.cursor_offset());
+            }
+
+            KeyCode::Char('l') => {
+                self.list_results();
+            }
+
+            KeyCode::Char(']') => {
+                self.find_difference(true);
+            }
+
+            KeyCode::Char('[') => {
+                self.find_difference(false);
+            }
+
+            KeyCode::Char('D') => {
+                self.summarize_differences();
+            }
+
+            KeyCode::Char('A') => {
+                self.toggle_alignment();
+            }
+
+            KeyCode::Char('c') => {
+                self.columns = self.columns.next();
+                self.status = match self.columns {
+                    Columns::Auto => "Bytes per line: fit to width".to_owned(),
+                    Columns::Fixed(count) => format!("Bytes per line: {}", count),
+                };
+            }
+
+            KeyCode::Char('P') => {
+                self.list_partitions();
+            }
+
+            KeyCode::Char('M') => {
+                if self.file_info.regions().is_empty() {
+                    self.status = "No memory map, open a process with --pid.".to_owned();
+                } else {
+                    self.mode = AppMode::Regions;
+                    self.status = format!("{} mapped region(s).", self.file_info.regions().len());
+                    self.selected_region = self
+                        .file_info
+                        .regions()
+                        .partition_point(|region| region.end <= self.cursor_offset())
+                        .min(self.file_info.regions().len() - 1);
                 }
+            }
 
-                KeyCode::Up => {
-                    self.selected_line -= 1;
-                    if self.selected_line <= 0 {
-                        self.selected_line = 0;
-                        self.file_info.scroll(sedecim_file_info::MoveValues::UpLine);
+            KeyCode::Char('I') => {
+                self.show_inspector = !self.show_inspector;
+            }
+
+            KeyCode::Char('v') => {
+                self.mode = match self.mode {
+                    AppMode::Visual => AppMode::Standard,
+                    _ => {
+                        self.selection_anchor = Some(self.cursor_offset());
+                        AppMode::Visual
                     }
+                };
+            }
+
+            KeyCode::Char('p') if matches!(self.mode, AppMode::Edit | AppMode::Insert) => {
+                self.paste();
+            }
+
+            KeyCode::Char('x') => {
+                self.mode = AppMode::SavePatch;
+                self.patch_path = if self.file_info.has_path() {
+                    format!("{}.bps", self.file_info.file_name)
+                } else {
+                    String::default()
+                };
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Char('i') | KeyCode::Insert | KeyCode::Char('X') if self.read_only => {
+                self.status = READ_ONLY_STATUS.to_owned();
+            }
+
+            KeyCode::Char('i') => {
+                self.mode = match self.mode {
+                    AppMode::Standard => AppMode::Edit,
+                    _ => AppMode::Standard,
+                };
+            }
+
+            KeyCode::Char('X') => {
+                self.mode = AppMode::ApplyPatch;
+                self.patch_path = String::default();
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Insert => {
+                self.mode = match self.mode {
+                    AppMode::Edit => AppMode::Insert,
+                    _ => AppMode::Edit,
+                };
+            }
+
+            KeyCode::Delete if matches!(self.mode, AppMode::Edit | AppMode::Insert) => {
+                let result = self.file_info.delete_byte(self.cursor_offset());
+                self.report(result);
+            }
+
+            KeyCode::Backspace if matches!(self.mode, AppMode::Insert) => {
+                let address = self.cursor_offset();
+                if address > 0 {
+                    self.move_cursor_to(address - 1);
+                    let result = self.file_info.delete_byte(address - 1);
+                    self.report(result);
                 }
+            }
 
-                KeyCode::Down => {
-                    self.selected_line += 1;
-                    if self.selected_line >= 19 {
-                        self.selected_line = 19;
-                        self.file_info
-                            .scroll(sedecim_file_info::MoveValues::DownLine);
-                    }
+            KeyCode::Char('u') => {
+                self.undo();
+            }
+
+            KeyCode::Char('z') if event.modifiers == KeyModifiers::CONTROL => {
+                self.undo();
+            }
+
+            KeyCode::Char('r') | KeyCode::Char('y') if event.modifiers == KeyModifiers::CONTROL => {
+                self.redo();
+            }
+
+            KeyCode::Char('s') | KeyCode::Char('o')
+                if event.modifiers == KeyModifiers::CONTROL && self.read_only =>
+            {
+                self.status = READ_ONLY_STATUS.to_owned();
+            }
+
+            KeyCode::Char('s') if event.modifiers == KeyModifiers::CONTROL => {
+                self.save();
+            }
+
+            KeyCode::Char('o') if event.modifiers == KeyModifiers::CONTROL => {
+                self.mode = AppMode::SaveAs;
+                self.save_path = if self.file_info.has_path() {
+                    self.file_info.file_name.clone()
+                } else {
+                    String::default()
+                };
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Char('q') => {
+                if !self.file_info.has_changes() {
+                    return true;
+                }
+                self.mode = AppMode::ConfirmQuit;
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Up => {
+                self.selected_line -= 1;
+                if self.selected_line <= 0 {
+                    self.selected_line = 0;
+                    self.file_info.scroll(sedecim_file_info::MoveValues::UpLine);
                 }
+            }
 
-                KeyCode::Right => {
-                    self.selected_value += 1;
-                    if self.selected_value > 9 {
-                        self.selected_value = 0;
-                    }
+            KeyCode::Down => {
+                self.move_down();
+            }
+
+            KeyCode::Right => {
+                self.selected_value += 1;
+                if self.selected_value > self.last_value() {
+                    self.selected_value = 0;
                 }
+            }
 
-                KeyCode::Left => {
-                    self.selected_value -= 1;
-                    if self.selected_value < 0 {
-                        self.selected_value = 9;
-                    }
+            KeyCode::Left => {
+                self.selected_value -= 1;
+                if self.selected_value < 0 {
+                    self.selected_value = self.last_value();
                 }
-                KeyCode::PageUp => {
-                    self.file_info.scroll(sedecim_file_info::MoveValues::UpPage);
+            }
+            KeyCode::PageUp => {
+                self.file_info.scroll(sedecim_file_info::MoveValues::UpPage);
+            }
+
+            KeyCode::PageDown => {
+                self.file_info
+                    .scroll(sedecim_file_info::MoveValues::DownPage);
+            }
+
+            KeyCode::Esc => {
+                self.mode = AppMode::Standard;
+            }
+
+            _ => {}
+        }
+
+        // The selection only lasts as long as visual mode.
+        if !matches!(self.mode, AppMode::Visual) {
+            self.selection_anchor = None;
+        }
+
+        false
+    }
+
+    /// Runs a command on the selected range, returning false for keys that
+    /// are not selection commands.
+    fn handle_key_visual(&mut self, event: KeyEvent) -> bool {
+        let Some((start, end)) = self.selection() else {
+            return false;
+        };
+        let length = end - start + 1;
+
+        match event.code {
+            KeyCode::Char('f') if self.read_only => {
+                self.status = READ_ONLY_STATUS.to_owned();
+            }
+
+            KeyCode::Char('y') => {
+                self.copy(start, length);
+                self.mode = AppMode::Standard;
+                self.selection_anchor = None;
+            }
+
+            KeyCode::Char('f') => {
+                self.mode = AppMode::Fill;
+                self.fill_value = String::default();
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Char('w') => {
+                self.mode = AppMode::Export;
+                self.export_path = String::default();
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Char('k') => {
+                self.status = match checksum::checksum_range(&self.file_info, start, length) {
+                    Ok(text) => text,
+                    Err(err) => format!("Checksum failed: {}", err),
+                };
+            }
+
+            KeyCode::Char('=') => {
+                let mut data = vec![0; length.min(64) as usize];
+                self.status = match self.file_info.read_at(start, &mut data) {
+                    Ok(read) => interpret(&data[..read]),
+                    Err(err) => format!("Read failed: {}", err),
+                };
+            }
+
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Copies `length` bytes from `start` into the clipboard.
+    fn copy(&mut self, start: u64, length: u64) {
+        if length > MAX_CLIPBOARD {
+            self.status = format!(
+                "Selection is too large to copy, the limit is {} bytes.",
+                MAX_CLIPBOARD
+            );
+            return;
+        }
+
+        let mut data = vec![0; length as usize];
+        match self.file_info.read_at(start, &mut data) {
+            Ok(read) => {
+                data.truncate(read);
+                self.status = format!("Copied {} byte(s).", read);
+                self.clipboard = data;
+            }
+            Err(err) => self.status = format!("Copy failed: {}", err),
+        }
+    }
+
+    /// Writes the clipboard at the cursor, inserting it in insert mode and
+    /// overwriting otherwise.
+    fn paste(&mut self) {
+        if self.clipboard.is_empty() {
+            self.status = "Clipboard is empty.".to_owned();
+            return;
+        }
+
+        let address = self.cursor_offset();
+        let result = if matches!(self.mode, AppMode::Insert) {
+            self.file_info.insert_bytes(address, &self.clipboard);
+            Ok(())
+        } else {
+            self.file_info.set_bytes(address, &self.clipboard)
+        };
+        self.status = match result {
+            Ok(()) => format!("Pasted {} byte(s).", self.clipboard.len()),
+            Err(err) => format!("Paste failed: {}", err),
+        };
+    }
+
+    /// Clicking a byte in either pane moves the cursor to it, dragging selects
+    /// the bytes between the press and the pointer and the wheel scrolls.
+    fn handle_mouse(&mut self, event: MouseEvent) {
+        if !matches!(
+            self.mode,
+            AppMode::Standard | AppMode::Edit | AppMode::Insert | AppMode::Visual
+        ) {
+            return;
+        }
+
+        match event.kind {
+            MouseEventKind::ScrollUp => {
+                self.finish_nibble_edit();
+                for _ in 0..WHEEL_LINES {
+                    self.file_info.scroll(sedecim_file_info::MoveValues::UpLine);
                 }
+            }
 
-                KeyCode::PageDown => {
+            MouseEventKind::ScrollDown => {
+                self.finish_nibble_edit();
+                for _ in 0..WHEEL_LINES {
                     self.file_info
-                        .scroll(sedecim_file_info::MoveValues::DownPage);
+                        .scroll(sedecim_file_info::MoveValues::DownLine);
                 }
+            }
 
-                KeyCode::Esc => match self.mode {
-                    AppMode::Jump => {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(offset) = ui::offset_at(self, event.column, event.row) {
+                    self.finish_nibble_edit();
+                    if matches!(self.mode, AppMode::Visual) {
                         self.mode = AppMode::Standard;
                     }
-                    _ => {}
-                },
+                    self.selection_anchor = None;
+                    self.drag_start = Some(offset);
+                    self.move_cursor_to(offset);
+                }
+            }
 
-                _ => {}
-            },
-            events::Event::Tick => {}
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let Some(start) = self.drag_start else {
+                    return;
+                };
+
+                // Dragging past the top or bottom of the view scrolls it.
+                let area = self.hex_area;
+                let row = if event.row < area.y {
+                    self.file_info.scroll(sedecim_file_info::MoveValues::UpLine);
+                    area.y
+                } else if event.row >= area.bottom() {
+                    self.file_info
+                        .scroll(sedecim_file_info::MoveValues::DownLine);
+                    area.bottom().saturating_sub(1)
+                } else {
+                    event.row
+                };
+
+                if let Some(offset) = ui::offset_at(self, event.column, row) {
+                    self.mode = AppMode::Visual;
+                    self.selection_anchor = Some(start);
+                    self.move_cursor_to(offset);
+                }
+            }
+
+            MouseEventKind::Up(MouseButton::Left) => {
+                self.drag_start = None;
+            }
+
+            _ => {}
         }
+    }
 
-        false
+    /// Index of the last line on screen.
+    fn last_line(&self) -> i32 {
+        self.file_info.visible_lines as i32 - 1
+    }
+
+    /// Index of the last byte on a line.
+    fn last_value(&self) -> i32 {
+        self.file_info.line_size as i32 - 1
+    }
+
+    fn move_down(&mut self) {
+        self.selected_line += 1;
+        if self.selected_line >= self.last_line() {
+            self.selected_line = self.last_line();
+            self.file_info
+                .scroll(sedecim_file_info::MoveValues::DownLine);
+        }
+    }
+
+    fn move_right(&mut self) {
+        self.selected_value += 1;
+        if self.selected_value > self.last_value() {
+            self.selected_value = 0;
+            self.move_down();
+        }
+    }
+
+    /// Replaces the high then the low nibble of the byte under the cursor,
+    /// moving on to the next byte once both have been typed. In insert mode
+    /// the first nibble inserts a new byte before the cursor.
+    fn edit_nibble(&mut self, chr: char) {
+        let address = self.cursor_offset();
+        let Some(nibble) = chr.to_digit(16).map(|n| n as u8) else {
+            return;
+        };
+        let inserting = matches!(self.mode, AppMode::Insert);
+
+        if self.edit_low_nibble {
+            if let Some(current) = self.byte_at(address) {
+                let value = (current & 0xf0) | nibble;
+                let result = if inserting {
+                    self.file_info.amend_inserted_byte(address, value)
+                } else {
+                    self.file_info.set_byte(address, value)
+                };
+                self.report(result);
+            }
+            self.finish_nibble_edit();
+
+            if inserting || address + 1 < self.file_info.file_size {
+                self.move_right();
+            }
+        } else if inserting {
+            self.file_info.begin_edit_group();
+            self.file_info.insert_byte(address, nibble << 4);
+            self.edit_low_nibble = true;
+        } else if let Some(current) = self.byte_at(address) {
+            self.file_info.begin_edit_group();
+            let result = self
+                .file_info
+                .set_byte(address, (nibble << 4) | (current & 0x0f));
+            self.report(result);
+            self.edit_low_nibble = true;
+        }
+    }
+
+    /// Reads the byte at `address`, showing a failed read in the status bar.
+    fn byte_at(&mut self, address: u64) -> Option<u8> {
+        match self.file_info.get_byte(address) {
+            Ok(value) => value,
+            Err(err) => {
+                self.status = err.to_string();
+                None
+            }
+        }
+    }
+
+    /// Shows a failed file operation in the status bar.
+    fn report(&mut self, result: Result<(), SedecimError>) {
+        if let Err(err) = result {
+            self.status = err.to_string();
+        }
+    }
+
+    fn finish_nibble_edit(&mut self) {
+        if self.edit_low_nibble {
+            self.edit_low_nibble = false;
+            self.file_info.end_edit_group();
+        }
+    }
+
+    /// Moves the cursor to `address`, scrolling when it is not on screen.
+    fn move_cursor_to(&mut self, address: u64) {
+        let line_size = self.file_info.line_size;
+        let file_offset = self.file_info.file_offset;
+        if address < file_offset || address >= file_offset + self.file_info.page_size() {
+            self.file_info.set_address(address - address % line_size);
+        }
+
+        let relative = address - self.file_info.file_offset;
+        self.selected_line = (relative / line_size) as i32;
+        self.selected_value = (relative % line_size) as i32;
+    }
+
+    fn undo(&mut self) {
+        self.status = match self.file_info.undo().map(describe_changes) {
+            Some((offset, summary)) => {
+                self.move_cursor_to(offset);
+                format!("Undid {}", summary)
+            }
+            None => "Nothing to undo.".to_owned(),
+        };
+    }
+
+    fn redo(&mut self) {
+        self.status = match self.file_info.redo().map(describe_changes) {
+            Some((offset, summary)) => {
+                self.move_cursor_to(offset);
+                format!("Redid {}", summary)
+            }
+            None => "Nothing to redo.".to_owned(),
+        };
     }
 
-    fn handle_input_jump(&mut self) -> bool {
-        match self.events.next() {
-            events::Event::Input(event) => match event.code {
-                KeyCode::Esc => self.mode = AppMode::Standard,
+    fn handle_input_jump(&mut self, event: KeyEvent) -> bool {
+        match event.code {
+            KeyCode::Esc => self.mode = AppMode::Standard,
+
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.jump_sector = !self.jump_sector;
+                self.jump_value.clear();
+                self.error = "".to_owned();
+            }
 
-                KeyCode::Char(chr) => match "0123456789abcdef".chars().position(|c| c == chr) {
-                    Some(_) => {
-                        self.jump_value.push(chr);
+            KeyCode::Char(chr)
+                if chr.is_ascii_digit() || !self.jump_sector && "abcdef".contains(chr) =>
+            {
+                self.jump_value.push(chr);
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Backspace => {
+                let _ = self.jump_value.pop();
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Enter => {
+                let address = if self.jump_sector {
+                    self.jump_value
+                        .parse::<u64>()
+                        .ok()
+                        .and_then(|sector| sector.checked_mul(self.sector_size()))
+                } else {
+                    u64::from_str_radix(&self.jump_value, 16).ok()
+                };
+
+                match address {
+                    Some(address) if address <= self.file_info.file_size => {
+                        self.mode = AppMode::Standard;
                         self.error = "".to_owned();
+
+                        self.move_cursor_to(address);
                     }
-                    None => {}
-                },
+                    _ if self.jump_sector => self.error = "Invalid Sector.".to_owned(),
+                    _ => self.error = "Invalid Address.".to_owned(),
+                }
+            }
+            _ => {}
+        }
+
+        false
+    }
+
+    fn handle_input_search(&mut self, event: KeyEvent) -> bool {
+        match event.code {
+            KeyCode::Esc => self.mode = AppMode::Standard,
+
+            KeyCode::Tab => {
+                self.search_kind = self.search_kind.next();
+                self.error = "".to_owned();
+            }
+
+            KeyCode::BackTab => {
+                self.search_kind = self.search_kind.previous();
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Char('t') if event.modifiers == KeyModifiers::CONTROL => {
+                self.search_ignore_case = !self.search_ignore_case;
+            }
+
+            KeyCode::Char(chr)
+                if !event.modifiers.contains(KeyModifiers::CONTROL)
+                    && (self.search_kind != search::SearchKind::Hex
+                        || chr.is_ascii_hexdigit()
+                        || chr == ' '
+                        || chr == '?') =>
+            {
+                self.search_value.push(chr);
+                self.error = "".to_owned();
+            }
 
-                KeyCode::Backspace => {
-                    let _ = self.jump_value.pop();
+            KeyCode::Backspace => {
+                let _ = self.search_value.pop();
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Enter => match search::build_pattern(
+                self.search_kind,
+                &self.search_value,
+                self.search_ignore_case,
+            ) {
+                Ok(pattern) => {
+                    self.search_pattern = Some(pattern);
+                    self.mode = AppMode::Standard;
                     self.error = "".to_owned();
+                    self.find_pattern(true, self.cursor_offset());
                 }
+                Err(err) => self.error = err,
+            },
+            _ => {}
+        }
 
-                KeyCode::Enter => {
-                    let address = i64::from_str_radix(&self.jump_value, 16).unwrap_or(-1);
-                    let max_address = self.file_info.file_size as i64;
+        false
+    }
 
-                    if address >= 0 && address <= max_address {
-                        let offset = address % 10;
-                        let address = address - offset;
+    /// Moves the cursor to the next match at or after `from`, or the previous
+    /// match before it, wrapping around the ends of the file.
+    fn find_pattern(&mut self, forward: bool, from: u64) {
+        let Some(pattern) = &self.search_pattern else {
+            self.status = "No search pattern, press / to search.".to_owned();
+            return;
+        };
 
-                        self.selected_value = offset as i32;
-                        self.selected_line = 0;
-                        self.mode = AppMode::Standard;
-                        self.error = "".to_owned();
+        let note = pattern.limit_note();
+        let result = if forward {
+            search::find_next(&self.file_info, pattern, from)
+        } else {
+            search::find_previous(&self.file_info, pattern, from)
+        };
+
+        self.status = match result {
+            Ok(Some((offset, wrapped))) => {
+                self.move_cursor_to(offset);
+                if wrapped {
+                    format!("Found at {:06x} (wrapped around)", offset)
+                } else {
+                    format!("Found at {:06x}", offset)
+                }
+            }
+            Ok(None) => "Pattern not found.".to_owned(),
+            Err(err) => format!("Search failed: {}", err),
+        };
+        self.add_limit_note(note);
+    }
+
+    /// Follows the status with a warning about the search pattern, if any.
+    fn add_limit_note(&mut self, note: Option<String>) {
+        if let Some(note) = note {
+            self.status = format!("{} {}", self.status, note);
+        }
+    }
+
+    /// Collects every match of the current pattern into the results list.
+    fn list_results(&mut self) {
+        let Some(pattern) = &self.search_pattern else {
+            self.status = "No search pattern, press / to search.".to_owned();
+            return;
+        };
+
+        let note = pattern.limit_note();
+        match search::find_all(&self.file_info, pattern) {
+            Ok((results, _)) if results.is_empty() => {
+                self.status = "Pattern not found.".to_owned();
+            }
+            Ok((results, truncated)) => {
+                self.status = if truncated {
+                    format!("Showing the first {} matches.", results.len())
+                } else {
+                    format!("{} match(es).", results.len())
+                };
+                self.search_results = results;
+                self.selected_result = 0;
+                self.mode = AppMode::Results;
+                self.move_cursor_to(self.search_results[0]);
+            }
+            Err(err) => self.status = format!("Search failed: {}", err),
+        }
+        self.add_limit_note(note);
+    }
+
+    fn handle_input_results(&mut self, event: KeyEvent) -> bool {
+        let page = self.file_info.visible_lines as usize;
+        if !move_in_list(
+            event,
+            &mut self.selected_result,
+            self.search_results.len(),
+            page,
+        ) {
+            self.mode = AppMode::Standard;
+        }
+
+        if let Some(offset) = self.search_results.get(self.selected_result) {
+            self.move_cursor_to(*offset);
+        }
+
+        false
+    }
+
+    /// Moves the cursor to the next or previous region where the compared
+    /// files differ.
+    fn find_difference(&mut self, forward: bool) {
+        let Some(compare) = &self.compare else {
+            self.status = "No file to compare with, open one with --compare.".to_owned();
+            return;
+        };
+
+        let from = self.cursor_offset();
+        if let Some(hunks) = compare.current_alignment(self.file_info.revision()) {
+            let hunk = if forward {
+                diff::next_difference(hunks, from)
+            } else {
+                diff::previous_difference(hunks, from)
+            };
+            let Some(hunk) = hunk.copied() else {
+                self.status = if forward {
+                    "No more differences after the cursor.".to_owned()
+                } else {
+                    "No more differences before the cursor.".to_owned()
+                };
+                return;
+            };
+            self.move_cursor_to(hunk.a_start.min(self.file_info.file_size));
+            self.status = format!(
+                "{} at {:06x}: {} -> {} byte(s) at {:06x}",
+                hunk.kind.label(),
+                hunk.a_start,
+                hunk.a_length,
+                hunk.b_length,
+                hunk.b_start
+            );
+            return;
+        }
+
+        let result = if forward {
+            compare::next_difference(&self.file_info, &compare.file_info, from)
+        } else {
+            compare::previous_difference(&self.file_info, &compare.file_info, from)
+        };
+
+        self.status = match result {
+            Ok(Some(offset)) if offset <= self.file_info.file_size => {
+                self.move_cursor_to(offset);
+                format!("Difference at {:06x}", offset)
+            }
+            // Bytes only the other file has cannot be scrolled to.
+            Ok(Some(offset)) => {
+                format!("The other file continues past the end, from {:06x}", offset)
+            }
+            Ok(None) if forward => "No more differences after the cursor.".to_owned(),
+            Ok(None) => "No more differences before the cursor.".to_owned(),
+            Err(err) => format!("Compare failed: {}", err),
+        };
+    }
+
+    /// Shows how many regions of the compared files differ and where,
+    /// aligning them again first when they are aligned.
+    fn summarize_differences(&mut self) {
+        let Some(compare) = &mut self.compare else {
+            self.status = "No file to compare with, open one with --compare.".to_owned();
+            return;
+        };
+
+        if compare.alignment.is_some() {
+            self.align_compared();
+            return;
+        }
+
+        self.status = match compare::diff_regions(&self.file_info, &compare.file_info) {
+            Ok(regions) => compare::summarize(&regions),
+            Err(err) => format!("Compare failed: {}", err),
+        };
+    }
+
+    /// Switches the compare view between the same offsets in both files and
+    /// lining them up around inserted and deleted bytes. An alignment made
+    /// out of date by edits is worked out again instead.
+    fn toggle_alignment(&mut self) {
+        let Some(compare) = &mut self.compare else {
+            self.status = "No file to compare with, open one with --compare.".to_owned();
+            return;
+        };
+
+        if !compare.is_alignment_stale(self.file_info.revision())
+            && compare.alignment.take().is_some()
+        {
+            self.status = "Comparing bytes at the same offsets.".to_owned();
+            return;
+        }
+        self.align_compared();
+    }
+
+    /// Aligns the compared files and shows a summary of how they differ.
+    fn align_compared(&mut self) {
+        let Some(compare) = &mut self.compare else {
+            return;
+        };
+
+        self.status = match diff::align_files(&self.file_info, &compare.file_info) {
+            Ok(hunks) => {
+                let summary = diff::summarize(&hunks);
+                compare.alignment = Some(hunks);
+                compare.aligned_revision = self.file_info.revision();
+                summary
+            }
+            Err(err) => format!("Align failed: {}", err),
+        };
+    }
+
+    /// Size of the sectors counted by the sector jump: that of the partition
+    /// table once one has been read, otherwise 512 bytes.
+    pub fn sector_size(&self) -> u64 {
+        self.partition_table
+            .as_ref()
+            .map_or(byte_source::SECTOR_SIZE, |table| table.sector_size)
+    }
+
+    /// Reads the MBR or GPT partition table into the partition list.
+    fn list_partitions(&mut self) {
+        match partitions::read_table(&self.file_info) {
+            Ok(None) => self.status = "No MBR or GPT partition table found.".to_owned(),
+            Ok(Some(table)) if table.partitions.is_empty() => {
+                self.status = format!("The {} partition table is empty.", table.kind.label());
+                self.partition_table = Some(table);
+            }
+            Ok(Some(table)) => {
+                self.status = format!(
+                    "{} partition table, {} partition(s).",
+                    table.kind.label(),
+                    table.partitions.len()
+                );
+                self.partition_table = Some(table);
+                self.selected_partition = 0;
+                self.move_to_partition();
+                self.mode = AppMode::Partitions;
+            }
+            Err(err) => self.status = format!("Reading the partition table failed: {}", err),
+        }
+    }
+
+    fn handle_input_partitions(&mut self, event: KeyEvent) -> bool {
+        let Some(table) = &self.partition_table else {
+            self.mode = AppMode::Standard;
+            return false;
+        };
+
+        let page = self.file_info.visible_lines as usize;
+        if !move_in_list(
+            event,
+            &mut self.selected_partition,
+            table.partitions.len(),
+            page,
+        ) {
+            self.mode = AppMode::Standard;
+        }
+
+        self.move_to_partition();
+
+        false
+    }
+
+    /// Moves the cursor to the start of the selected partition, or to the
+    /// end of the data for a partition past it, as in a truncated image.
+    fn move_to_partition(&mut self) {
+        let start = self
+            .partition_table
+            .as_ref()
+            .and_then(|table| table.partitions.get(self.selected_partition))
+            .map(|partition| partition.start);
+        if let Some(start) = start {
+            self.move_cursor_to(start.min(self.file_info.file_size));
+        }
+    }
+
+    fn handle_input_regions(&mut self, event: KeyEvent) -> bool {
+        let count = self.file_info.regions().len();
+        let page = self.file_info.visible_lines as usize;
+        if !move_in_list(event, &mut self.selected_region, count, page) {
+            self.mode = AppMode::Standard;
+        }
+
+        if let Some(start) = self
+            .file_info
+            .regions()
+            .get(self.selected_region)
+            .map(|region| region.start)
+        {
+            self.move_cursor_to(start);
+        }
+
+        false
+    }
+
+    /// Saves pending edits, reporting the outcome in the status bar.
+    fn save(&mut self) -> bool {
+        match self.file_info.save() {
+            Ok(()) => {
+                self.status = format!("Saved {}", self.file_info.file_name);
+                true
+            }
+            Err(err) => {
+                self.status = format!("Save failed: {}", err);
+                false
+            }
+        }
+    }
+
+    fn handle_input_save_as(&mut self, event: KeyEvent) -> bool {
+        match event.code {
+            KeyCode::Esc => self.mode = AppMode::Standard,
+
+            KeyCode::Char(chr) => {
+                self.save_path.push(chr);
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Backspace => {
+                let _ = self.save_path.pop();
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Enter => match self.file_info.save_as(&self.save_path) {
+                Ok(()) => {
+                    self.mode = AppMode::Standard;
+                    self.status = format!("Saved {}", self.file_info.file_name);
+                }
+                Err(err) => {
+                    self.error = format!("Save failed: {}", err);
+                }
+            },
+            _ => {}
+        }
 
-                        self.file_info.set_address(address as u64);
-                    } else {
-                        self.error = "Invalid Address.".to_owned();
+        false
+    }
+
+    fn handle_input_confirm_quit(&mut self, event: KeyEvent) -> bool {
+        match event.code {
+            KeyCode::Char('y') => {
+                if self.save() {
+                    return true;
+                }
+                self.mode = AppMode::Standard;
+            }
+
+            KeyCode::Char('n') => return true,
+
+            KeyCode::Char('c') | KeyCode::Esc => self.mode = AppMode::Standard,
+            _ => {}
+        }
+
+        false
+    }
+
+    fn handle_input_fill(&mut self, event: KeyEvent) -> bool {
+        match event.code {
+            KeyCode::Esc => self.mode = AppMode::Visual,
+
+            KeyCode::Char(chr) if chr.is_ascii_hexdigit() || chr == ' ' => {
+                self.fill_value.push(chr);
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Backspace => {
+                let _ = self.fill_value.pop();
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Enter => match (parse_hex_bytes(&self.fill_value), self.selection()) {
+                (Ok(_), Some((start, end))) if end - start + 1 > MAX_FILL => {
+                    self.error = format!(
+                        "Selection is too large to fill, the limit is {} bytes.",
+                        MAX_FILL
+                    );
+                }
+                (Ok(pattern), Some((start, end))) => {
+                    let data: Vec<u8> = pattern
+                        .iter()
+                        .cycle()
+                        .take((end - start + 1) as usize)
+                        .copied()
+                        .collect();
+                    match self.file_info.set_bytes(start, &data) {
+                        Ok(()) => {
+                            self.mode = AppMode::Visual;
+                            self.status = format!("Filled {} byte(s).", data.len());
+                        }
+                        Err(err) => self.error = format!("Fill failed: {}", err),
                     }
                 }
-                _ => {}
+                (Err(err), _) => self.error = err,
+                (_, None) => self.mode = AppMode::Standard,
             },
+            _ => {}
+        }
+
+        false
+    }
+
+    fn handle_input_export(&mut self, event: KeyEvent) -> bool {
+        match event.code {
+            KeyCode::Esc => self.mode = AppMode::Visual,
 
-            events::Event::Tick => {}
+            KeyCode::Char(chr) => {
+                self.export_path.push(chr);
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Backspace => {
+                let _ = self.export_path.pop();
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Enter => {
+                let Some((start, end)) = self.selection() else {
+                    self.mode = AppMode::Standard;
+                    return false;
+                };
+
+                let result = self.check_output_path(&self.export_path).and_then(|_| {
+                    self.file_info
+                        .write_range(start, end - start + 1, &self.export_path)
+                });
+                match result {
+                    Ok(()) => {
+                        self.mode = AppMode::Visual;
+                        self.status = format!(
+                            "Exported {} byte(s) to {}",
+                            end - start + 1,
+                            self.export_path
+                        );
+                    }
+                    Err(err) => self.error = format!("Export failed: {}", err),
+                }
+            }
+            _ => {}
         }
 
         false
     }
 
-    fn handle_input_help(&mut self) -> bool {
-        match self.events.next() {
-            events::Event::Input(_) => {
-                self.mode = AppMode::Standard;
+    /// Fails when `path` is one of the files being viewed, including the one
+    /// it is compared with.
+    fn check_output_path(&self, path: &str) -> Result<(), SedecimError> {
+        self.file_info.check_output_path(path)?;
+        match &self.compare {
+            Some(compare) => compare.file_info.check_output_path(path),
+            None => Ok(()),
+        }
+    }
+
+    fn handle_input_save_patch(&mut self, event: KeyEvent) -> bool {
+        match event.code {
+            KeyCode::Esc => self.mode = AppMode::Standard,
+
+            KeyCode::Char(chr) => {
+                self.patch_path.push(chr);
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Backspace => {
+                let _ = self.patch_path.pop();
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Enter => match self
+                .check_output_path(&self.patch_path)
+                .map_err(patch::PatchError::from)
+                .and_then(|_| patch::save_edits(&self.file_info, &self.patch_path))
+            {
+                Ok((format, size)) => {
+                    self.mode = AppMode::Standard;
+                    self.status = format!(
+                        "Saved the pending edits as a {} byte {} patch to {}",
+                        size,
+                        format.label(),
+                        self.patch_path
+                    );
+                }
+                Err(err) => self.error = format!("Patch failed: {}", err),
+            },
+            _ => {}
+        }
+
+        false
+    }
+
+    fn handle_input_apply_patch(&mut self, event: KeyEvent) -> bool {
+        match event.code {
+            KeyCode::Esc => self.mode = AppMode::Standard,
+
+            KeyCode::Char(chr) => {
+                self.patch_path.push(chr);
+                self.error = "".to_owned();
+            }
+
+            KeyCode::Backspace => {
+                let _ = self.patch_path.pop();
+                self.error = "".to_owned();
             }
-            events::Event::Tick => {}
+
+            KeyCode::Enter => match patch::apply_to(&mut self.file_info, &self.patch_path) {
+                Ok(format) => {
+                    self.mode = AppMode::Standard;
+                    // The data may have become shorter.
+                    self.move_cursor_to(self.cursor_offset().min(self.file_info.file_size));
+                    self.status = format!(
+                        "Applied the {} patch {}, undo with u",
+                        format.label(),
+                        self.patch_path
+                    );
+                }
+                Err(err) => self.error = format!("Patch failed: {}", err),
+            },
+            _ => {}
         }
+
         false
     }
+
+    fn handle_input_help(&mut self, _event: KeyEvent) -> bool {
+        self.mode = AppMode::Standard;
+        false
+    }
+}
+
+/// Leaves the alternate screen and raw mode. Safe to call more than once.
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        ShowCursor
+    );
+}
+
+/// Moves the selection of a side list of `count` entries for a key press.
+/// Returns false when the key closes the list.
+fn move_in_list(event: KeyEvent, selected: &mut usize, count: usize, page: usize) -> bool {
+    let last = count.saturating_sub(1);
+    match event.code {
+        KeyCode::Esc | KeyCode::Enter => return false,
+        KeyCode::Up => *selected = selected.saturating_sub(1),
+        KeyCode::Down => *selected = (*selected + 1).min(last),
+        KeyCode::PageUp => *selected = selected.saturating_sub(page),
+        KeyCode::PageDown => *selected = (*selected + page).min(last),
+        KeyCode::Home => *selected = 0,
+        KeyCode::End => *selected = last,
+        _ => {}
+    }
+    true
+}
+
+/// Summarizes an undo step for the status bar, returning the offset of its
+/// first change along with the text.
+fn describe_changes(changes: &[edit_journal::ByteChange]) -> (u64, String) {
+    let first = &changes[0];
+    let detail = match (first.old_value, first.new_value) {
+        (Some(old), Some(new)) => format!("{:02x} -> {:02x}", old, new),
+        (None, Some(new)) => format!("insert {:02x}", new),
+        (Some(old), None) => format!("delete {:02x}", old),
+        (None, None) => "".to_owned(),
+    };
+
+    (
+        first.offset,
+        format!(
+            "{} byte change(s) from {} ({:06x}: {})",
+            changes.len(),
+            first.timestamp.format("%H:%M:%S"),
+            first.offset,
+            detail
+        ),
+    )
+}
+
+/// Parses hex bytes typed at a prompt, e.g. "de ad be ef".
+fn parse_hex_bytes(text: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = text
+        .chars()
+        .filter_map(|chr| chr.to_digit(16))
+        .map(|digit| digit as u8)
+        .collect();
+
+    if digits.is_empty() {
+        return Err("Enter at least one hex byte.".to_owned());
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err("Enter two hex digits per byte.".to_owned());
+    }
+
+    Ok(digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect())
+}
+
+/// Describes selected bytes as a little and big endian integer when they fit
+/// in one, and as text otherwise.
+fn interpret(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "Nothing selected.".to_owned();
+    }
+
+    if data.len() > 8 {
+        let text: String = String::from_utf8_lossy(data)
+            .chars()
+            .map(|chr| if chr.is_control() { '.' } else { chr })
+            .collect();
+        return format!("Text: \"{}\"", text);
+    }
+
+    let shift = 64 - 8 * data.len() as u32;
+    let little = data
+        .iter()
+        .rev()
+        .fold(0u64, |acc, value| (acc << 8) | *value as u64);
+    let big = data
+        .iter()
+        .fold(0u64, |acc, value| (acc << 8) | *value as u64);
+    format!(
+        "{}-bit LE {} ({}) | BE {} ({})",
+        data.len() * 8,
+        little,
+        ((little << shift) as i64) >> shift,
+        big,
+        ((big << shift) as i64) >> shift
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex_bytes_pairs_digits() {
+        assert_eq!(
+            parse_hex_bytes("de ad b e").unwrap(),
+            vec![0xde, 0xad, 0xbe]
+        );
+        assert!(parse_hex_bytes("abc").is_err());
+        assert!(parse_hex_bytes(" ").is_err());
+    }
+
+    #[test]
+    fn interpret_integers_and_text() {
+        assert_eq!(
+            interpret(&[0xfe, 0xff]),
+            "16-bit LE 65534 (-2) | BE 65279 (-257)"
+        );
+        assert_eq!(interpret(b"hello, world"), "Text: \"hello, world\"");
+    }
 }
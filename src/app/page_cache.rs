@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use super::byte_source::ByteSource;
+use super::sedecim_file_page::SedecimFilePage;
+
+/// Pages of the original data kept in memory, about 1 MiB.
+pub const CACHE_PAGES: usize = 4096;
+
+/// Pages of the original data, dropping the least recently used once
+/// `capacity` pages are held.
+pub struct PageCache {
+    capacity: usize,
+    /// Each page with the time it was last used.
+    pages: HashMap<u64, (Arc<SedecimFilePage>, u64)>,
+    /// Page ids by the time they were last used, oldest first.
+    recent: BTreeMap<u64, u64>,
+    clock: u64,
+}
+
+impl PageCache {
+    pub fn new(capacity: usize) -> PageCache {
+        PageCache {
+            capacity: capacity.max(1),
+            pages: HashMap::new(),
+            recent: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Returns the page, marking it as the most recently used.
+    pub fn get(&mut self, page_id: u64) -> Option<Arc<SedecimFilePage>> {
+        let (page, used) = self.pages.get_mut(&page_id)?;
+        self.recent.remove(used);
+        self.clock += 1;
+        *used = self.clock;
+        self.recent.insert(self.clock, page_id);
+        Some(page.clone())
+    }
+
+    pub fn contains(&self, page_id: u64) -> bool {
+        self.pages.contains_key(&page_id)
+    }
+
+    pub fn insert(&mut self, page: Arc<SedecimFilePage>) {
+        let page_id = page.page_id;
+        if let Some((_, used)) = self.pages.remove(&page_id) {
+            self.recent.remove(&used);
+        }
+
+        while self.pages.len() >= self.capacity {
+            let Some((_, oldest)) = self.recent.pop_first() else {
+                break;
+            };
+            self.pages.remove(&oldest);
+        }
+
+        self.clock += 1;
+        self.pages.insert(page_id, (page, self.clock));
+        self.recent.insert(self.clock, page_id);
+    }
+
+    pub fn clear(&mut self) {
+        self.pages.clear();
+        self.recent.clear();
+    }
+}
+
+/// Reads pages on a background thread, so pages near the view are usually
+/// cached by the time they are scrolled to. Pages that fail to load are
+/// dropped; reading them again on demand reports the error.
+pub struct Prefetcher {
+    requests: Sender<u64>,
+    loaded: Receiver<(u64, Option<SedecimFilePage>)>,
+    pending: HashSet<u64>,
+}
+
+impl Prefetcher {
+    /// Starts the reader thread, which stops once the prefetcher is dropped.
+    pub fn new(source: Arc<dyn ByteSource>) -> Prefetcher {
+        let (requests, request_rx) = mpsc::channel::<u64>();
+        let (loaded_tx, loaded) = mpsc::channel();
+
+        thread::spawn(move || {
+            for page_id in request_rx {
+                let page = SedecimFilePage::load(&*source, page_id).ok();
+                if loaded_tx.send((page_id, page)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Prefetcher {
+            requests,
+            loaded,
+            pending: HashSet::new(),
+        }
+    }
+
+    /// Asks for a page to be read, unless it already has been.
+    pub fn request(&mut self, page_id: u64) {
+        if self.pending.insert(page_id) && self.requests.send(page_id).is_err() {
+            self.pending.remove(&page_id);
+        }
+    }
+
+    /// Moves the pages read so far into `cache`.
+    pub fn receive(&mut self, cache: &mut PageCache) {
+        for (page_id, page) in self.loaded.try_iter() {
+            self.pending.remove(&page_id);
+            if let Some(page) = page {
+                cache.insert(Arc::new(page));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::byte_source::MemorySource;
+    use std::time::{Duration, Instant};
+
+    fn page(page_id: u64) -> Arc<SedecimFilePage> {
+        let mut page = SedecimFilePage::new();
+        page.page_id = page_id;
+        Arc::new(page)
+    }
+
+    #[test]
+    fn drops_least_recently_used() {
+        let mut cache = PageCache::new(2);
+        cache.insert(page(1));
+        cache.insert(page(2));
+        assert!(cache.get(1).is_some());
+
+        cache.insert(page(3));
+        assert!(cache.contains(1));
+        assert!(!cache.contains(2));
+        assert!(cache.contains(3));
+    }
+
+    #[test]
+    fn prefetches_in_the_background() {
+        let data: Vec<u8> = (0..1000).map(|i| (i % 256) as u8).collect();
+        let mut prefetcher = Prefetcher::new(Arc::new(MemorySource::new(data)));
+        let mut cache = PageCache::new(8);
+
+        prefetcher.request(1);
+        prefetcher.request(3);
+        let start = Instant::now();
+        while !(cache.contains(1) && cache.contains(3)) && start.elapsed() < Duration::from_secs(5)
+        {
+            prefetcher.receive(&mut cache);
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(cache.get(1).unwrap().buffer[0], 250);
+        assert_eq!(cache.get(3).unwrap().buffer[..4], [238, 239, 240, 241]);
+    }
+}
@@ -0,0 +1,420 @@
+use std::io;
+
+use super::byte_source::SECTOR_SIZE;
+use super::sedecim_file_info::SedecimFileInfo;
+
+/// Offset of the four primary entries in a master boot record.
+const MBR_ENTRIES: u64 = 446;
+const MBR_ENTRY_SIZE: u64 = 16;
+const MBR_SIGNATURE: [u8; 2] = [0x55, 0xaa];
+/// Partition type of the single MBR entry covering a GPT disk.
+const PROTECTIVE_TYPE: u8 = 0xee;
+/// Logical partitions followed before the chain is taken to be a loop.
+const MAX_LOGICAL: usize = 128;
+
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+/// Sector sizes tried when looking for a GPT header, which is in sector 1.
+const GPT_SECTOR_SIZES: [u64; 2] = [SECTOR_SIZE, 4096];
+/// Entries beyond this are not listed, as no real table has that many.
+const MAX_GPT_ENTRIES: u32 = 1024;
+/// Largest GPT entry accepted. Entries are at least 128 bytes and a multiple
+/// of 8; real ones are 128.
+const MAX_GPT_ENTRY_SIZE: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TableKind {
+    Mbr,
+    Gpt,
+}
+
+impl TableKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            TableKind::Mbr => "MBR",
+            TableKind::Gpt => "GPT",
+        }
+    }
+}
+
+/// One partition, with its position in bytes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Partition {
+    /// Partition number as used by the operating system, from 1.
+    pub number: usize,
+    pub start: u64,
+    pub length: u64,
+    pub kind: String,
+    /// The GPT partition name, empty for MBR partitions.
+    pub name: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PartitionTable {
+    pub kind: TableKind,
+    /// Size of the sectors the table counts in.
+    pub sector_size: u64,
+    pub partitions: Vec<Partition>,
+}
+
+/// Reads the partition table at the start of the data, with all edits
+/// applied. Returns `None` when there is no MBR signature; GPT disks are
+/// recognised by their protective MBR.
+pub fn read_table(file_info: &SedecimFileInfo) -> io::Result<Option<PartitionTable>> {
+    let Some(entries) = read_mbr(file_info, 0)? else {
+        return Ok(None);
+    };
+
+    if entries.iter().any(|entry| entry.kind == PROTECTIVE_TYPE) {
+        for sector_size in GPT_SECTOR_SIZES {
+            if let Some(partitions) = read_gpt(file_info, sector_size)? {
+                return Ok(Some(PartitionTable {
+                    kind: TableKind::Gpt,
+                    sector_size,
+                    partitions,
+                }));
+            }
+        }
+    }
+
+    let mut partitions = vec![];
+    let mut extended = None;
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.kind == 0 {
+            continue;
+        }
+        if is_extended(entry.kind) {
+            extended.get_or_insert(entry.first);
+        }
+        partitions.push(entry.partition(index + 1, 0));
+    }
+    if let Some(first) = extended {
+        read_logical(file_info, first, &mut partitions)?;
+    }
+
+    Ok(Some(PartitionTable {
+        kind: TableKind::Mbr,
+        sector_size: SECTOR_SIZE,
+        partitions,
+    }))
+}
+
+/// An entry of a boot record, counted in sectors.
+struct MbrEntry {
+    kind: u8,
+    first: u64,
+    sectors: u64,
+}
+
+impl MbrEntry {
+    /// The entry as a partition, where `base` is the sector its start is
+    /// counted from.
+    fn partition(&self, number: usize, base: u64) -> Partition {
+        Partition {
+            number,
+            start: (base + self.first) * SECTOR_SIZE,
+            length: self.sectors * SECTOR_SIZE,
+            kind: mbr_type_name(self.kind).to_owned(),
+            name: String::new(),
+        }
+    }
+}
+
+/// Reads the four entries of the boot record in `sector`, or `None` when it
+/// has no boot signature.
+fn read_mbr(file_info: &SedecimFileInfo, sector: u64) -> io::Result<Option<Vec<MbrEntry>>> {
+    let mut record = [0; SECTOR_SIZE as usize];
+    if file_info.read_at(sector * SECTOR_SIZE, &mut record)? < record.len()
+        || record[510..] != MBR_SIGNATURE
+    {
+        return Ok(None);
+    }
+
+    let entries = (0..4)
+        .map(|index| {
+            let entry = &record[(MBR_ENTRIES + index * MBR_ENTRY_SIZE) as usize..];
+            MbrEntry {
+                kind: entry[4],
+                first: u32_at(entry, 8) as u64,
+                sectors: u32_at(entry, 12) as u64,
+            }
+        })
+        .collect();
+    Ok(Some(entries))
+}
+
+/// Follows the chain of extended boot records from sector `first`, adding
+/// the logical partitions numbered from 5.
+///
+/// Each record holds one logical partition, counted from the record itself,
+/// and a link to the next record, counted from the start of the extended
+/// partition.
+fn read_logical(
+    file_info: &SedecimFileInfo,
+    first: u64,
+    partitions: &mut Vec<Partition>,
+) -> io::Result<()> {
+    let mut record = first;
+    for number in 5..5 + MAX_LOGICAL {
+        let Some(entries) = read_mbr(file_info, record)? else {
+            break;
+        };
+        if entries[0].kind != 0 {
+            partitions.push(entries[0].partition(number, record));
+        }
+        if !is_extended(entries[1].kind) || entries[1].first == 0 {
+            break;
+        }
+        record = first + entries[1].first;
+    }
+    Ok(())
+}
+
+fn is_extended(kind: u8) -> bool {
+    matches!(kind, 0x05 | 0x0f | 0x85)
+}
+
+/// Reads the GPT whose header is in sector 1, or `None` when there is no
+/// header there.
+fn read_gpt(file_info: &SedecimFileInfo, sector_size: u64) -> io::Result<Option<Vec<Partition>>> {
+    let mut header = [0; 92];
+    if file_info.read_at(sector_size, &mut header)? < header.len() || &header[..8] != GPT_SIGNATURE
+    {
+        return Ok(None);
+    }
+
+    let Some(entries_start) = u64_at(&header, 72).checked_mul(sector_size) else {
+        return Ok(None);
+    };
+    let count = u32_at(&header, 80).min(MAX_GPT_ENTRIES);
+    let entry_size = u32_at(&header, 84) as usize;
+    if !(128..=MAX_GPT_ENTRY_SIZE).contains(&entry_size) || !entry_size.is_multiple_of(8) {
+        return Ok(None);
+    }
+    let Some(entries_size) = (count as usize).checked_mul(entry_size) else {
+        return Ok(None);
+    };
+
+    let mut entries = vec![0; entries_size];
+    let read = file_info.read_at(entries_start, &mut entries)?;
+
+    let mut partitions = vec![];
+    for (index, entry) in entries[..read].chunks_exact(entry_size).enumerate() {
+        let type_guid = &entry[..16];
+        if type_guid.iter().all(|value| *value == 0) {
+            continue;
+        }
+
+        let first = u64_at(entry, 32);
+        let last = u64_at(entry, 40);
+        let name: Vec<u16> = entry[56..128]
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .take_while(|unit| *unit != 0)
+            .collect();
+        partitions.push(Partition {
+            number: index + 1,
+            start: first.saturating_mul(sector_size),
+            length: last
+                .saturating_add(1)
+                .saturating_sub(first)
+                .saturating_mul(sector_size),
+            kind: gpt_type_name(type_guid),
+            name: String::from_utf16_lossy(&name),
+        });
+    }
+    Ok(Some(partitions))
+}
+
+fn mbr_type_name(kind: u8) -> &'static str {
+    match kind {
+        0x01 => "FAT12",
+        0x04 | 0x06 | 0x0e => "FAT16",
+        0x05 | 0x0f | 0x85 => "Extended",
+        0x07 => "NTFS/exFAT",
+        0x0b | 0x0c => "FAT32",
+        0x82 => "Linux swap",
+        0x83 => "Linux",
+        0x8e => "Linux LVM",
+        0xa5 => "FreeBSD",
+        0xa8 => "Apple UFS",
+        0xaf => "Apple HFS",
+        0xee => "GPT protective",
+        0xef => "EFI System",
+        0xfd => "Linux RAID",
+        _ => "Unknown",
+    }
+}
+
+/// Names the common partition types, showing the GUID of any other.
+fn gpt_type_name(guid: &[u8]) -> String {
+    let guid = format_guid(guid);
+    let name = match guid.as_str() {
+        "C12A7328-F81F-11D2-BA4B-00A0C93EC93B" => "EFI System",
+        "21686148-6449-6E6F-744E-656564454649" => "BIOS boot",
+        "E3C9E316-0B5C-4DB8-817D-F92DF00215AE" => "Microsoft reserved",
+        "EBD0A0A2-B9E5-4433-87C0-68B6B72699C7" => "Microsoft basic data",
+        "DE94BBA4-06D1-4D40-A16A-BFD50179D6AC" => "Windows recovery",
+        "0FC63DAF-8483-4772-8E79-3D69D8477DE4" => "Linux filesystem",
+        "0657FD6D-A4AB-43C4-84E5-0933C84B4F4F" => "Linux swap",
+        "E6D6D379-F507-44C2-A23C-238F2A3DF928" => "Linux LVM",
+        "A19D880F-05FC-4D3B-A006-743F0F84911E" => "Linux RAID",
+        "4F68BCE3-E8CD-4DB1-96E7-FBCAF984B709" => "Linux root (x86-64)",
+        "933AC7E1-2EB4-4F13-B844-0E14E2AEF915" => "Linux home",
+        "48465300-0000-11AA-AA11-00306543ECAC" => "Apple HFS+",
+        "7C3457EF-0000-11AA-AA11-00306543ECAC" => "Apple APFS",
+        _ => return guid,
+    };
+    name.to_owned()
+}
+
+/// Formats a GUID stored with its first three fields little endian.
+fn format_guid(guid: &[u8]) -> String {
+    let hex = |bytes: &[u8]| -> String { bytes.iter().map(|b| format!("{:02X}", b)).collect() };
+    let reversed =
+        |bytes: &[u8]| -> String { hex(&bytes.iter().rev().copied().collect::<Vec<_>>()) };
+    format!(
+        "{}-{}-{}-{}-{}",
+        reversed(&guid[0..4]),
+        reversed(&guid[4..6]),
+        reversed(&guid[6..8]),
+        hex(&guid[8..10]),
+        hex(&guid[10..16])
+    )
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// Formats a size with the largest binary unit that keeps it at least 1.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_files::open;
+
+    /// Writes an MBR entry into the record in `sector`.
+    fn mbr_entry(disk: &mut [u8], sector: u64, index: u64, kind: u8, first: u32, sectors: u32) {
+        let record = (sector * SECTOR_SIZE) as usize;
+        let entry = record + (MBR_ENTRIES + index * MBR_ENTRY_SIZE) as usize;
+        disk[entry + 4] = kind;
+        disk[entry + 8..entry + 12].copy_from_slice(&first.to_le_bytes());
+        disk[entry + 12..entry + 16].copy_from_slice(&sectors.to_le_bytes());
+        disk[record + 510..record + 512].copy_from_slice(&MBR_SIGNATURE);
+    }
+
+    #[test]
+    fn reads_primary_and_logical_mbr_partitions() {
+        let mut disk = vec![0; 64 * 512];
+        mbr_entry(&mut disk, 0, 0, 0x83, 2, 10);
+        mbr_entry(&mut disk, 0, 1, 0x05, 20, 40);
+        // The first logical partition and a link to the next record.
+        mbr_entry(&mut disk, 20, 0, 0x07, 1, 5);
+        mbr_entry(&mut disk, 20, 1, 0x05, 10, 10);
+        mbr_entry(&mut disk, 30, 0, 0x82, 2, 4);
+
+        let (info, _file) = open(&disk);
+        let table = read_table(&info).unwrap().unwrap();
+        assert_eq!(table.kind, TableKind::Mbr);
+        let found: Vec<(usize, u64, u64, &str)> = table
+            .partitions
+            .iter()
+            .map(|p| (p.number, p.start / 512, p.length / 512, p.kind.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (1, 2, 10, "Linux"),
+                (2, 20, 40, "Extended"),
+                (5, 21, 5, "NTFS/exFAT"),
+                (6, 32, 4, "Linux swap"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_gpt_partitions() {
+        let mut disk = vec![0; 64 * 512];
+        mbr_entry(&mut disk, 0, 0, PROTECTIVE_TYPE, 1, 63);
+        let header = 512;
+        disk[header..header + 8].copy_from_slice(GPT_SIGNATURE);
+        disk[header + 72..header + 80].copy_from_slice(&2u64.to_le_bytes());
+        disk[header + 80..header + 84].copy_from_slice(&4u32.to_le_bytes());
+        disk[header + 84..header + 88].copy_from_slice(&128u32.to_le_bytes());
+
+        let entry = 1024 + 128;
+        let linux = [
+            0xaf, 0x3d, 0xc6, 0x0f, 0x83, 0x84, 0x72, 0x47, 0x8e, 0x79, 0x3d, 0x69, 0xd8, 0x47,
+            0x7d, 0xe4,
+        ];
+        disk[entry..entry + 16].copy_from_slice(&linux);
+        disk[entry + 32..entry + 40].copy_from_slice(&34u64.to_le_bytes());
+        disk[entry + 40..entry + 48].copy_from_slice(&63u64.to_le_bytes());
+        for (index, unit) in "root".encode_utf16().enumerate() {
+            disk[entry + 56 + 2 * index..entry + 58 + 2 * index]
+                .copy_from_slice(&unit.to_le_bytes());
+        }
+
+        let (info, _file) = open(&disk);
+        let table = read_table(&info).unwrap().unwrap();
+        assert_eq!(table.kind, TableKind::Gpt);
+        assert_eq!(
+            table.partitions,
+            [Partition {
+                number: 2,
+                start: 34 * 512,
+                length: 30 * 512,
+                kind: "Linux filesystem".to_owned(),
+                name: "root".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn ignores_gpt_headers_with_bad_entry_sizes() {
+        for entry_size in [64u32, 130, 8192, u32::MAX] {
+            let mut disk = vec![0; 64 * 512];
+            mbr_entry(&mut disk, 0, 0, PROTECTIVE_TYPE, 1, 63);
+            let header = 512;
+            disk[header..header + 8].copy_from_slice(GPT_SIGNATURE);
+            disk[header + 72..header + 80].copy_from_slice(&2u64.to_le_bytes());
+            disk[header + 80..header + 84].copy_from_slice(&1024u32.to_le_bytes());
+            disk[header + 84..header + 88].copy_from_slice(&entry_size.to_le_bytes());
+
+            let (info, _file) = open(&disk);
+            let table = read_table(&info).unwrap().unwrap();
+            assert_eq!(table.kind, TableKind::Mbr);
+        }
+    }
+
+    #[test]
+    fn data_without_a_signature_has_no_table() {
+        let (info, _file) = open(&[0; 1024]);
+        assert_eq!(read_table(&info).unwrap(), None);
+    }
+
+    #[test]
+    fn sizes_use_binary_units() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(8 << 30), "8.0 GiB");
+    }
+}
@@ -0,0 +1,997 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use thiserror::Error;
+
+use super::diff::{self, Hunk, HunkKind};
+use super::edit_journal::MAX_GROUP_CHANGES;
+use super::error::SedecimError;
+use super::sedecim_file_info::{SedecimFileInfo, MAX_LOAD_SIZE};
+
+/// IPS offsets and sizes are 3 bytes, so must be below this.
+const IPS_LIMIT: usize = 0x100_0000;
+
+/// Longest IPS record: sizes are 2 bytes.
+const IPS_MAX_RECORD: usize = 0xffff;
+
+/// An IPS record at this offset would read as the end marker.
+const IPS_EOF: usize = 0x45_4f46;
+
+/// Patch file formats, as used for ROM and firmware fixes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PatchFormat {
+    /// International Patching System: overwritten bytes at offsets below
+    /// 16 MiB.
+    Ips,
+    /// Beat patches: copies from the source or the target so far, and new
+    /// bytes, with CRC-32 checks.
+    Bps,
+    /// Universal patches: bytes XORed in place, with CRC-32 checks.
+    Ups,
+}
+
+impl PatchFormat {
+    pub fn parse(text: &str) -> Option<PatchFormat> {
+        match text.to_ascii_lowercase().as_str() {
+            "ips" => Some(PatchFormat::Ips),
+            "bps" => Some(PatchFormat::Bps),
+            "ups" => Some(PatchFormat::Ups),
+            _ => None,
+        }
+    }
+
+    /// The format named by the extension of `path`, such as `fix.bps`.
+    pub fn from_path(path: &str) -> Option<PatchFormat> {
+        Path::new(path)
+            .extension()
+            .and_then(|extension| PatchFormat::parse(&extension.to_string_lossy()))
+    }
+
+    /// The format of a patch, from its first bytes.
+    fn detect(patch: &[u8]) -> Option<PatchFormat> {
+        [PatchFormat::Ips, PatchFormat::Bps, PatchFormat::Ups]
+            .into_iter()
+            .find(|format| patch.starts_with(format.magic()))
+    }
+
+    fn magic(self) -> &'static [u8] {
+        match self {
+            PatchFormat::Ips => b"PATCH",
+            PatchFormat::Bps => b"BPS1",
+            PatchFormat::Ups => b"UPS1",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "IPS",
+            PatchFormat::Bps => "BPS",
+            PatchFormat::Ups => "UPS",
+        }
+    }
+}
+
+/// Problems creating or applying a patch. Checksums are CRC-32.
+#[derive(Debug, Error)]
+pub enum PatchError {
+    #[error("not an IPS, BPS or UPS patch")]
+    UnknownFormat,
+
+    #[error("{path}: use a .ips, .bps or .ups file name for the patch")]
+    UnknownExtension { path: String },
+
+    #[error("{format} patch ends early")]
+    Truncated { format: &'static str },
+
+    #[error("{format} patch is malformed at byte {offset:#x}")]
+    Malformed { format: &'static str, offset: usize },
+
+    #[error("{format} patch is damaged: its checksum is {found:08x}, expected {expected:08x}")]
+    PatchChecksum {
+        format: &'static str,
+        expected: u32,
+        found: u32,
+    },
+
+    #[error("the patch is for a {expected} byte file, this one has {found} bytes")]
+    SourceSize { expected: u64, found: u64 },
+
+    #[error(
+        "the patch is for a different file: its checksum is {found:08x}, expected {expected:08x}"
+    )]
+    SourceChecksum { expected: u32, found: u32 },
+
+    #[error("the patched data is wrong: its checksum is {found:08x}, expected {expected:08x}")]
+    TargetChecksum { expected: u32, found: u32 },
+
+    #[error("IPS patches only reach the first 16 MiB, the data has {size} bytes")]
+    TooLarge { size: u64 },
+
+    #[error("the patch makes a {size} byte file, the limit is {limit} MiB")]
+    TargetTooLarge { size: u64, limit: u64 },
+
+    #[error("{path} has {size} bytes, patches only work on files up to {limit} MiB")]
+    FileTooLarge { path: String, size: u64, limit: u64 },
+
+    #[error(
+        "the patch changes {count} bytes, at most {limit} can be applied as edits that can be undone; use `sedecim patch apply` instead"
+    )]
+    TooManyChanges { count: u64, limit: u64 },
+
+    #[error("there are no pending edits to save as a patch")]
+    NoChanges,
+
+    #[error(transparent)]
+    File(#[from] SedecimError),
+
+    #[error("{path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// Writes a patch turning `source` into `target`.
+pub fn create(format: PatchFormat, source: &[u8], target: &[u8]) -> Result<Vec<u8>, PatchError> {
+    match format {
+        PatchFormat::Ips => create_ips(source, target),
+        PatchFormat::Bps => Ok(create_bps(source, target)),
+        PatchFormat::Ups => Ok(create_ups(source, target)),
+    }
+}
+
+/// Applies a patch of any of the formats to `source`, returning the format
+/// and the patched data.
+pub fn apply(patch: &[u8], source: &[u8]) -> Result<(PatchFormat, Vec<u8>), PatchError> {
+    let format = PatchFormat::detect(patch).ok_or(PatchError::UnknownFormat)?;
+    let target = match format {
+        PatchFormat::Ips => apply_ips(patch, source)?,
+        PatchFormat::Bps => apply_bps(patch, source)?,
+        PatchFormat::Ups => apply_ups(patch, source)?,
+    };
+    Ok((format, target))
+}
+
+/// Writes the pending edits of the open file to `path` as a patch, in the
+/// format given by its extension, returning the format and patch size.
+pub fn save_edits(
+    file_info: &SedecimFileInfo,
+    path: &str,
+) -> Result<(PatchFormat, usize), PatchError> {
+    let format = PatchFormat::from_path(path).ok_or_else(|| PatchError::UnknownExtension {
+        path: path.to_owned(),
+    })?;
+    if !file_info.has_changes() {
+        return Err(PatchError::NoChanges);
+    }
+
+    file_info.check_output_path(path)?;
+    check_file_size(file_info, file_info.original_size())?;
+    check_file_size(file_info, file_info.file_size)?;
+    let patch = create(format, &file_info.load_original()?, &file_info.load()?)?;
+    write_patch(path, &patch)?;
+    Ok((format, patch.len()))
+}
+
+/// Applies the patch at `path` to the open file as pending edits, undone
+/// as a single step, returning its format. Every changed byte is journaled,
+/// so patches changing more than `MAX_GROUP_CHANGES` bytes are refused
+/// unless the file keeps no undo history.
+pub fn apply_to(file_info: &mut SedecimFileInfo, path: &str) -> Result<PatchFormat, PatchError> {
+    let limit = file_info.keeps_history().then_some(MAX_GROUP_CHANGES);
+    apply_with_limit(file_info, path, limit)
+}
+
+fn apply_with_limit(
+    file_info: &mut SedecimFileInfo,
+    path: &str,
+    limit: Option<u64>,
+) -> Result<PatchFormat, PatchError> {
+    check_file_size(file_info, file_info.file_size)?;
+    let patch = fs::read(path).map_err(|source| PatchError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let current = file_info.load()?;
+    let (format, target) = apply(&patch, &current)?;
+
+    let hunks = changes(&current, &target);
+    let count = hunks
+        .iter()
+        .map(|hunk| {
+            if hunk.a_length == hunk.b_length {
+                hunk.a_length
+            } else {
+                hunk.a_length + hunk.b_length
+            }
+        })
+        .sum();
+    if let Some(limit) = limit.filter(|limit| count > *limit) {
+        return Err(PatchError::TooManyChanges { count, limit });
+    }
+
+    file_info.begin_edit_group();
+    let result = replace_contents(file_info, &hunks, &target);
+    file_info.end_edit_group();
+    result?;
+    Ok(format)
+}
+
+/// The stretches that differ between `current` and `target`. Data that
+/// keeps its size is compared at the same offsets, so it can still be saved
+/// in place; otherwise the two are aligned around inserted and deleted
+/// bytes.
+fn changes(current: &[u8], target: &[u8]) -> Vec<Hunk> {
+    if current.len() != target.len() {
+        return diff::align(current, target)
+            .into_iter()
+            .filter(|hunk| hunk.kind != HunkKind::Equal)
+            .collect();
+    }
+
+    let mut hunks = vec![];
+    let mut position = 0;
+    while position < target.len() {
+        if current[position] == target[position] {
+            position += 1;
+            continue;
+        }
+        let end = (position..target.len())
+            .find(|index| current[*index] == target[*index])
+            .unwrap_or(target.len());
+        let length = (end - position) as u64;
+        hunks.push(Hunk {
+            kind: HunkKind::Change,
+            a_start: position as u64,
+            a_length: length,
+            b_start: position as u64,
+            b_length: length,
+        });
+        position = end;
+    }
+    hunks
+}
+
+/// Edits the open file into `target` by applying `hunks` from `changes`.
+fn replace_contents(
+    file_info: &mut SedecimFileInfo,
+    hunks: &[Hunk],
+    target: &[u8],
+) -> Result<(), SedecimError> {
+    // From the end, so the offsets of the hunks still to do stay valid.
+    for hunk in hunks.iter().rev() {
+        let replacement = &target[hunk.b_start as usize..(hunk.b_start + hunk.b_length) as usize];
+        if hunk.a_length == hunk.b_length {
+            file_info.set_bytes(hunk.a_start, replacement)?;
+        } else {
+            file_info.delete_bytes(hunk.a_start, hunk.a_length)?;
+            file_info.insert_bytes(hunk.a_start, replacement);
+        }
+    }
+    Ok(())
+}
+
+/// Writes a patch turning the file `old` into `new`, in `format` or the one
+/// given by the extension of `path`. Returns the format used.
+pub fn create_file(
+    format: Option<PatchFormat>,
+    old: &str,
+    new: &str,
+    path: &str,
+) -> Result<PatchFormat, PatchError> {
+    let format = format
+        .or_else(|| PatchFormat::from_path(path))
+        .ok_or_else(|| PatchError::UnknownExtension {
+            path: path.to_owned(),
+        })?;
+    let source = load_file(&SedecimFileInfo::new(old.to_owned())?)?;
+    let target = load_file(&SedecimFileInfo::new(new.to_owned())?)?;
+
+    write_patch(path, &create(format, &source, &target)?)?;
+    Ok(format)
+}
+
+/// Applies the patch at `path` to `file`, saving the result to `output`, or
+/// over `file` when there is none. Returns the patch format.
+pub fn apply_file(path: &str, file: &str, output: Option<&str>) -> Result<PatchFormat, PatchError> {
+    let mut file_info = SedecimFileInfo::new(file.to_owned())?;
+    // The result is saved right away, so no undo history is needed.
+    file_info.disable_history();
+    let format = apply_to(&mut file_info, path)?;
+    match output {
+        Some(output) => file_info.save_as(output)?,
+        None if file_info.has_changes() => file_info.save()?,
+        None => {}
+    }
+    Ok(format)
+}
+
+fn load_file(file_info: &SedecimFileInfo) -> Result<Vec<u8>, PatchError> {
+    check_file_size(file_info, file_info.file_size)?;
+    Ok(file_info.load()?)
+}
+
+/// Patches are made and applied in memory, so the files they cover have to
+/// fit there whole.
+fn check_file_size(file_info: &SedecimFileInfo, size: u64) -> Result<(), PatchError> {
+    if size > MAX_LOAD_SIZE {
+        return Err(PatchError::FileTooLarge {
+            path: file_info.display_name().to_owned(),
+            size,
+            limit: MAX_LOAD_SIZE >> 20,
+        });
+    }
+    Ok(())
+}
+
+fn write_patch(path: &str, patch: &[u8]) -> Result<(), PatchError> {
+    fs::write(path, patch).map_err(|source| PatchError::Io {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
+}
+
+/// Reads through a patch, reporting where it goes wrong.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+    format: &'static str,
+}
+
+impl<'a> Reader<'a> {
+    /// Reads `data` after the format's magic bytes.
+    fn new(data: &'a [u8], format: PatchFormat) -> Reader<'a> {
+        Reader {
+            data,
+            position: format.magic().len(),
+            format: format.label(),
+        }
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], PatchError> {
+        let bytes = self
+            .data
+            .get(self.position..self.position.saturating_add(count))
+            .ok_or(PatchError::Truncated {
+                format: self.format,
+            })?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, PatchError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn big_endian(&mut self, count: usize) -> Result<usize, PatchError> {
+        Ok(self
+            .take(count)?
+            .iter()
+            .fold(0, |value, byte| value << 8 | *byte as usize))
+    }
+
+    /// Reads a BPS/UPS number: 7 bits per byte, low bits first, the last
+    /// byte flagged by its top bit, and each further byte adding one more
+    /// so every number has a single encoding.
+    fn number(&mut self) -> Result<usize, PatchError> {
+        let start = self.position;
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte = self.byte()?;
+            value = ((byte & 0x7f) as usize)
+                .checked_mul(shift)
+                .and_then(|part| value.checked_add(part))
+                .ok_or_else(|| self.malformed(start))?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift
+                .checked_mul(0x80)
+                .ok_or_else(|| self.malformed(start))?;
+            value = value
+                .checked_add(shift)
+                .ok_or_else(|| self.malformed(start))?;
+        }
+    }
+
+    fn malformed(&self, offset: usize) -> PatchError {
+        PatchError::Malformed {
+            format: self.format,
+            offset,
+        }
+    }
+}
+
+fn push_number(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let low = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(0x80 | low);
+            return;
+        }
+        out.push(low);
+        value -= 1;
+    }
+}
+
+/// Checks the CRC-32 of a BPS or UPS patch, which covers all of it but the
+/// last 4 bytes, and returns the source and target checksums before it.
+fn read_footer(patch: &[u8], format: PatchFormat) -> Result<(u32, u32), PatchError> {
+    let body = patch
+        .len()
+        .checked_sub(12)
+        .filter(|body| *body >= format.magic().len());
+    let Some(body) = body else {
+        return Err(PatchError::Truncated {
+            format: format.label(),
+        });
+    };
+
+    let word =
+        |at: usize| u32::from_le_bytes([patch[at], patch[at + 1], patch[at + 2], patch[at + 3]]);
+    let expected = word(body + 8);
+    let found = crc32(&patch[..body + 8]);
+    if found != expected {
+        return Err(PatchError::PatchChecksum {
+            format: format.label(),
+            expected,
+            found,
+        });
+    }
+    Ok((word(body), word(body + 4)))
+}
+
+fn push_footer(out: &mut Vec<u8>, source: &[u8], target: &[u8]) {
+    out.extend(crc32(source).to_le_bytes());
+    out.extend(crc32(target).to_le_bytes());
+    out.extend(crc32(out).to_le_bytes());
+}
+
+/// Records every run of bytes that differs, or is past the end of `source`,
+/// and the new size when the data gets shorter.
+fn create_ips(source: &[u8], target: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let too_large = PatchError::TooLarge {
+        size: target.len() as u64,
+    };
+    let differs = |index: usize| source.get(index) != Some(&target[index]);
+    let mut out = PatchFormat::Ips.magic().to_vec();
+    let mut position = 0;
+    while position < target.len() {
+        if !differs(position) {
+            position += 1;
+            continue;
+        }
+
+        let start = if position == IPS_EOF {
+            position - 1
+        } else {
+            position
+        };
+        if start >= IPS_LIMIT {
+            return Err(too_large);
+        }
+        let mut end = position;
+        while end < target.len() && end - start < IPS_MAX_RECORD && differs(end) {
+            end += 1;
+        }
+        out.extend(&(start as u32).to_be_bytes()[1..]);
+        out.extend(((end - start) as u16).to_be_bytes());
+        out.extend(&target[start..end]);
+        position = end;
+    }
+    out.extend(b"EOF");
+    if target.len() < source.len() {
+        if target.len() >= IPS_LIMIT {
+            return Err(too_large);
+        }
+        out.extend(&(target.len() as u32).to_be_bytes()[1..]);
+    }
+
+    Ok(out)
+}
+
+fn apply_ips(patch: &[u8], source: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut reader = Reader::new(patch, PatchFormat::Ips);
+    let mut target = source.to_vec();
+    loop {
+        if reader.take(3)? == b"EOF" {
+            break;
+        }
+        reader.position -= 3;
+        let offset = reader.big_endian(3)?;
+        let size = reader.big_endian(2)?;
+        // A size of 0 starts a run of one repeated byte.
+        let (length, data) = match size {
+            0 => {
+                let length = reader.big_endian(2)?;
+                (length, None)
+            }
+            _ => (size, Some(reader.take(size)?)),
+        };
+        if target.len() < offset + length {
+            target.resize(offset + length, 0);
+        }
+        match data {
+            Some(data) => target[offset..offset + length].copy_from_slice(data),
+            None => {
+                let value = reader.byte()?;
+                target[offset..offset + length].fill(value);
+            }
+        }
+    }
+    // Some patches end with the size to cut the data to.
+    if reader.data.len() - reader.position >= 3 {
+        let size = reader.big_endian(3)?;
+        target.truncate(size);
+    }
+
+    Ok(target)
+}
+
+/// Lines the data up so moved and kept bytes are copied from the source,
+/// and only new bytes are stored in the patch.
+fn create_bps(source: &[u8], target: &[u8]) -> Vec<u8> {
+    const SOURCE_READ: usize = 0;
+    const TARGET_READ: usize = 1;
+    const SOURCE_COPY: usize = 2;
+
+    let mut out = PatchFormat::Bps.magic().to_vec();
+    push_number(&mut out, source.len());
+    push_number(&mut out, target.len());
+    // No metadata.
+    push_number(&mut out, 0);
+
+    let mut source_relative = 0;
+    for hunk in diff::align(source, target) {
+        let (a_start, b_start) = (hunk.a_start as usize, hunk.b_start as usize);
+        let (a_length, b_length) = (hunk.a_length as usize, hunk.b_length as usize);
+        match hunk.kind {
+            HunkKind::Equal if a_start == b_start => {
+                push_number(&mut out, (a_length - 1) << 2 | SOURCE_READ);
+            }
+            HunkKind::Equal => {
+                push_number(&mut out, (a_length - 1) << 2 | SOURCE_COPY);
+                let offset = a_start as isize - source_relative as isize;
+                push_number(&mut out, offset.unsigned_abs() << 1 | (offset < 0) as usize);
+                source_relative = a_start + a_length;
+            }
+            HunkKind::Insert | HunkKind::Change => {
+                push_number(&mut out, (b_length - 1) << 2 | TARGET_READ);
+                out.extend(&target[b_start..b_start + b_length]);
+            }
+            HunkKind::Delete => {}
+        }
+    }
+    push_footer(&mut out, source, target);
+
+    out
+}
+
+fn apply_bps(patch: &[u8], source: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (source_crc, target_crc) = read_footer(patch, PatchFormat::Bps)?;
+    let mut reader = Reader::new(&patch[..patch.len() - 12], PatchFormat::Bps);
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata_size = reader.number()?;
+    reader.take(metadata_size)?;
+    check_source(source, source_size, source_crc)?;
+    check_target_size(target_size)?;
+
+    let mut target: Vec<u8> = vec![];
+    let mut source_relative: usize = 0;
+    let mut target_relative: usize = 0;
+    while reader.position < reader.data.len() {
+        let start = reader.position;
+        let command = reader.number()?;
+        let length = (command >> 2) + 1;
+        if target
+            .len()
+            .checked_add(length)
+            .is_none_or(|end| end > target_size)
+        {
+            return Err(reader.malformed(start));
+        }
+        match command & 3 {
+            // Source read: the source bytes at the same offset.
+            0 => {
+                let data = source
+                    .get(target.len()..target.len() + length)
+                    .ok_or_else(|| reader.malformed(start))?;
+                target.extend_from_slice(data);
+            }
+            // Target read: bytes stored in the patch.
+            1 => target.extend_from_slice(reader.take(length)?),
+            // Source and target copy: from a moving offset into the source,
+            // or into the data written so far, which may overlap the bytes
+            // being written.
+            command => {
+                let offset = reader.number()?;
+                let relative = if command == 2 {
+                    &mut source_relative
+                } else {
+                    &mut target_relative
+                };
+                *relative = if offset & 1 == 0 {
+                    relative.checked_add(offset >> 1)
+                } else {
+                    relative.checked_sub(offset >> 1)
+                }
+                .ok_or_else(|| reader.malformed(start))?;
+                let end = relative
+                    .checked_add(length)
+                    .ok_or_else(|| reader.malformed(start))?;
+
+                if command == 2 {
+                    let data = source
+                        .get(*relative..end)
+                        .ok_or_else(|| reader.malformed(start))?;
+                    target.extend_from_slice(data);
+                } else {
+                    if *relative >= target.len() {
+                        return Err(reader.malformed(start));
+                    }
+                    for index in *relative..end {
+                        target.push(target[index]);
+                    }
+                }
+                *relative = end;
+            }
+        }
+    }
+
+    check_target(&target, target_crc)?;
+    Ok(target)
+}
+
+/// Stores the XOR of the two files wherever they differ, so the same patch
+/// also turns `target` back into `source`.
+fn create_ups(source: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut out = PatchFormat::Ups.magic().to_vec();
+    push_number(&mut out, source.len());
+    push_number(&mut out, target.len());
+
+    let length = source.len().max(target.len());
+    let xor = |index: usize| {
+        source.get(index).copied().unwrap_or(0) ^ target.get(index).copied().unwrap_or(0)
+    };
+    let mut last = 0;
+    let mut position = 0;
+    while position < length {
+        if xor(position) == 0 {
+            position += 1;
+            continue;
+        }
+        push_number(&mut out, position - last);
+        while position < length && xor(position) != 0 {
+            out.push(xor(position));
+            position += 1;
+        }
+        // The run ends with a zero, which stands for one equal byte.
+        out.push(0);
+        position += 1;
+        last = position;
+    }
+    push_footer(&mut out, source, target);
+
+    out
+}
+
+fn apply_ups(patch: &[u8], source: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (source_crc, target_crc) = read_footer(patch, PatchFormat::Ups)?;
+    let mut reader = Reader::new(&patch[..patch.len() - 12], PatchFormat::Ups);
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+
+    // The patch works both ways, so the target can be patched back.
+    let found = crc32(source);
+    let (target_size, target_crc) =
+        if found == target_crc && found != source_crc && source.len() == target_size {
+            (source_size, source_crc)
+        } else {
+            check_source(source, source_size, source_crc)?;
+            (target_size, target_crc)
+        };
+
+    check_target_size(target_size)?;
+    let mut target = source.to_vec();
+    target.resize(target_size, 0);
+    let mut position: usize = 0;
+    while reader.position < reader.data.len() {
+        let start = reader.position;
+        position = position
+            .checked_add(reader.number()?)
+            .ok_or_else(|| reader.malformed(start))?;
+        loop {
+            let value = reader.byte()?;
+            if let Some(byte) = target.get_mut(position) {
+                *byte ^= value;
+            }
+            position = position
+                .checked_add(1)
+                .ok_or_else(|| reader.malformed(start))?;
+            if value == 0 {
+                break;
+            }
+        }
+    }
+
+    check_target(&target, target_crc)?;
+    Ok(target)
+}
+
+fn check_source(source: &[u8], expected_size: usize, expected: u32) -> Result<(), PatchError> {
+    if source.len() != expected_size {
+        return Err(PatchError::SourceSize {
+            expected: expected_size as u64,
+            found: source.len() as u64,
+        });
+    }
+    let found = crc32(source);
+    if found != expected {
+        return Err(PatchError::SourceChecksum { expected, found });
+    }
+    Ok(())
+}
+
+/// Sizes are read from the patch, so a damaged or crafted one could ask for
+/// more memory than there is.
+fn check_target_size(size: usize) -> Result<(), PatchError> {
+    if size as u64 > MAX_LOAD_SIZE {
+        return Err(PatchError::TargetTooLarge {
+            size: size as u64,
+            limit: MAX_LOAD_SIZE >> 20,
+        });
+    }
+    Ok(())
+}
+
+fn check_target(target: &[u8], expected: u32) -> Result<(), PatchError> {
+    let found = crc32(target);
+    if found != expected {
+        return Err(PatchError::TargetChecksum { expected, found });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_files::{open, temp_file};
+
+    fn source() -> Vec<u8> {
+        (0..4096u32).map(|index| (index * 7 % 251) as u8).collect()
+    }
+
+    fn edited() -> Vec<u8> {
+        let mut target = source();
+        target[10..14].copy_from_slice(b"EDIT");
+        target.splice(2000..2000, *b"inserted");
+        target.drain(3000..3100);
+        target.extend(b"tail");
+        target
+    }
+
+    #[test]
+    fn round_trips_every_format() {
+        let (source, target) = (source(), edited());
+        for format in [PatchFormat::Ips, PatchFormat::Bps, PatchFormat::Ups] {
+            let patch = create(format, &source, &target).unwrap();
+            let (detected, patched) = apply(&patch, &source).unwrap();
+            assert_eq!(detected, format);
+            assert_eq!(patched, target, "{}", format.label());
+        }
+
+        // Shorter data, cut by the IPS end record.
+        let short = &source[..1000];
+        let patch = create(PatchFormat::Ips, &source, short).unwrap();
+        assert_eq!(apply(&patch, &source).unwrap().1, short);
+    }
+
+    #[test]
+    fn bps_copies_moved_bytes() {
+        let (source, target) = (source(), edited());
+        let patch = create(PatchFormat::Bps, &source, &target).unwrap();
+        // Only the new bytes are stored.
+        assert!(patch.len() < 100, "{} bytes", patch.len());
+    }
+
+    #[test]
+    fn ups_patches_back_to_the_source() {
+        let (source, target) = (source(), edited());
+        let patch = create(PatchFormat::Ups, &source, &target).unwrap();
+        assert_eq!(apply(&patch, &target).unwrap().1, source);
+    }
+
+    #[test]
+    fn reports_checksum_mismatches() {
+        let (source, target) = (source(), edited());
+        let mut other = source.clone();
+        other[0] ^= 1;
+
+        for format in [PatchFormat::Bps, PatchFormat::Ups] {
+            let patch = create(format, &source, &target).unwrap();
+            assert!(matches!(
+                apply(&patch, &other),
+                Err(PatchError::SourceChecksum { .. })
+            ));
+            assert!(matches!(
+                apply(&patch, &source[..100]),
+                Err(PatchError::SourceSize {
+                    expected: 4096,
+                    found: 100
+                })
+            ));
+
+            let mut damaged = patch.clone();
+            damaged[8] ^= 1;
+            assert!(matches!(
+                apply(&damaged, &source),
+                Err(PatchError::PatchChecksum { .. })
+            ));
+        }
+
+        // A patch whose stored target checksum is wrong.
+        let mut patch = create(PatchFormat::Bps, &source, &target).unwrap();
+        let footer = patch.len() - 12;
+        patch[footer + 4] ^= 1;
+        patch.truncate(patch.len() - 4);
+        let crc = crc32(&patch);
+        patch.extend(crc.to_le_bytes());
+        assert!(matches!(
+            apply(&patch, &source),
+            Err(PatchError::TargetChecksum { .. })
+        ));
+
+        assert!(matches!(
+            apply(b"nonsense", &source),
+            Err(PatchError::UnknownFormat)
+        ));
+    }
+
+    #[test]
+    fn rejects_huge_target_sizes() {
+        let source = source();
+        for format in [PatchFormat::Bps, PatchFormat::Ups] {
+            let mut patch = format.magic().to_vec();
+            push_number(&mut patch, source.len());
+            push_number(&mut patch, usize::MAX >> 2);
+            if format == PatchFormat::Bps {
+                push_number(&mut patch, 0);
+            }
+            push_footer(&mut patch, &source, &[]);
+            assert!(matches!(
+                apply(&patch, &source),
+                Err(PatchError::TargetTooLarge { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_copies_past_the_end_of_memory() {
+        let source = source();
+        for command in [2, 3] {
+            // A huge copy, then one from a huge offset.
+            for (length, offset) in [(usize::MAX >> 2, 0), (1, usize::MAX - 1)] {
+                let mut patch = PatchFormat::Bps.magic().to_vec();
+                push_number(&mut patch, source.len());
+                push_number(&mut patch, source.len());
+                push_number(&mut patch, 0);
+                push_number(&mut patch, 1);
+                push_number(&mut patch, ((length - 1) << 2) | command);
+                push_number(&mut patch, offset);
+                push_footer(&mut patch, &source, &source);
+                assert!(matches!(
+                    apply(&patch, &source),
+                    Err(PatchError::Malformed { .. })
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_ups_offsets_past_the_end_of_memory() {
+        let source = source();
+        // An XOR byte at the last offset, then a run ending there.
+        for (offset, run) in [(usize::MAX - 1, &[1, 0][..]), (usize::MAX, &[0][..])] {
+            let mut patch = PatchFormat::Ups.magic().to_vec();
+            push_number(&mut patch, source.len());
+            push_number(&mut patch, source.len());
+            push_number(&mut patch, offset);
+            patch.extend_from_slice(run);
+            push_footer(&mut patch, &source, &source);
+            assert!(matches!(
+                apply(&patch, &source),
+                Err(PatchError::Malformed { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn reads_ips_runs() {
+        // A run of 3 0xAA bytes at offset 2, then cut to 6 bytes.
+        let patch = b"PATCH\x00\x00\x02\x00\x00\x00\x03\xaaEOF\x00\x00\x06";
+        let (_, patched) = apply(patch, &[0; 8]).unwrap();
+        assert_eq!(patched, [0, 0, 0xaa, 0xaa, 0xaa, 0]);
+    }
+
+    #[test]
+    fn applies_to_the_open_file_as_one_edit() {
+        let (mut file_info, _file) = open(&source());
+        // Saving picks the format from the extension.
+        let patch_file = tempfile::Builder::new().suffix(".bps").tempfile().unwrap();
+        let patch_path = patch_file.path();
+        std::fs::write(
+            patch_path,
+            create(PatchFormat::Bps, &source(), &edited()).unwrap(),
+        )
+        .unwrap();
+
+        let format = apply_to(&mut file_info, &patch_path.to_string_lossy()).unwrap();
+        assert_eq!(format, PatchFormat::Bps);
+        assert_eq!(file_info.load().unwrap(), edited());
+
+        // The edits make the same patch again.
+        let (_, size) = save_edits(&file_info, &patch_path.to_string_lossy()).unwrap();
+        assert_eq!(size as u64, std::fs::metadata(patch_path).unwrap().len());
+        assert_eq!(
+            apply(&std::fs::read(patch_path).unwrap(), &source())
+                .unwrap()
+                .1,
+            edited()
+        );
+
+        assert!(file_info.undo().is_some());
+        assert_eq!(file_info.load().unwrap(), source());
+        assert!(!file_info.has_changes());
+    }
+
+    #[test]
+    fn refuses_patches_changing_too_many_bytes_to_undo() {
+        let (mut file_info, _file) = open(&source());
+        let patch_file = tempfile::Builder::new().suffix(".ups").tempfile().unwrap();
+        std::fs::write(
+            patch_file.path(),
+            create(PatchFormat::Ups, &source(), &edited()).unwrap(),
+        )
+        .unwrap();
+        let patch_path = patch_file.path().to_string_lossy().to_string();
+
+        assert!(matches!(
+            apply_with_limit(&mut file_info, &patch_path, Some(2)),
+            Err(PatchError::TooManyChanges { limit: 2, .. })
+        ));
+        assert!(!file_info.has_changes());
+
+        // Without an undo history there is no limit.
+        file_info.disable_history();
+        apply_to(&mut file_info, &patch_path).unwrap();
+        assert_eq!(file_info.load().unwrap(), edited());
+        assert!(file_info.undo().is_none());
+    }
+
+    #[test]
+    fn rejects_files_too_large_to_patch() {
+        let file = temp_file(&[]);
+        file.as_file().set_len(MAX_LOAD_SIZE + 1).unwrap();
+        let mut file_info =
+            SedecimFileInfo::new(file.path().to_string_lossy().to_string()).unwrap();
+
+        assert!(matches!(
+            apply_to(&mut file_info, "missing.bps"),
+            Err(PatchError::FileTooLarge { .. })
+        ));
+        file_info.set_byte(0, 1).unwrap();
+        assert!(matches!(
+            save_edits(&file_info, "edits.bps"),
+            Err(PatchError::FileTooLarge { .. })
+        ));
+    }
+}
@@ -0,0 +1,347 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PieceSource {
+    Original,
+    Added,
+}
+
+/// A run of bytes taken from either the original file or the append buffer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Piece {
+    pub source: PieceSource,
+    pub start: u64,
+    pub length: u64,
+}
+
+/// One change to the piece list: the `removed` pieces from `index` were
+/// swapped for the `inserted` ones. Swapping them back undoes it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PieceEdit {
+    pub index: usize,
+    pub removed: Vec<Piece>,
+    pub inserted: Vec<Piece>,
+}
+
+/// Describes the edited file as a list of pieces over the unmodified original
+/// and an append only buffer holding every byte that was typed in.
+pub struct PieceTable {
+    pieces: Vec<Piece>,
+    starts: Vec<u64>,
+    added: Vec<u8>,
+    original_length: u64,
+    length: u64,
+    /// Changes to the piece list not yet handed to the edit journal.
+    edits: Vec<PieceEdit>,
+}
+
+impl PieceTable {
+    pub fn new(original_length: u64) -> PieceTable {
+        let mut table = PieceTable {
+            pieces: Self::original_pieces(original_length),
+            starts: vec![],
+            added: vec![],
+            original_length,
+            length: 0,
+            edits: vec![],
+        };
+        table.reindex();
+        table
+    }
+
+    fn original_pieces(original_length: u64) -> Vec<Piece> {
+        if original_length == 0 {
+            return vec![];
+        }
+
+        vec![Piece {
+            source: PieceSource::Original,
+            start: 0,
+            length: original_length,
+        }]
+    }
+
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn pieces(&self) -> &[Piece] {
+        &self.pieces
+    }
+
+    pub fn added(&self) -> &[u8] {
+        &self.added
+    }
+
+    /// True when the table still describes the original file unchanged.
+    pub fn is_original(&self) -> bool {
+        self.pieces == Self::original_pieces(self.original_length)
+    }
+
+    /// Hands over the changes made to the piece list since the last call,
+    /// so the journal can undo them.
+    pub fn take_edits(&mut self) -> Vec<PieceEdit> {
+        std::mem::take(&mut self.edits)
+    }
+
+    /// Undoes `edits`, last first, returning the edits that redo them.
+    pub fn revert(&mut self, edits: Vec<PieceEdit>) -> Vec<PieceEdit> {
+        let mut reverted = vec![];
+        for edit in edits.into_iter().rev() {
+            let range = edit.index..edit.index + edit.inserted.len();
+            self.pieces.splice(range, edit.removed.iter().copied());
+            reverted.push(PieceEdit {
+                index: edit.index,
+                removed: edit.inserted,
+                inserted: edit.removed,
+            });
+        }
+        self.reindex();
+        reverted
+    }
+
+    /// Replaces `count` pieces from `index` with `pieces`, keeping the edit
+    /// for the journal.
+    fn splice(&mut self, index: usize, count: usize, pieces: Vec<Piece>) {
+        let removed = self
+            .pieces
+            .splice(index..index + count, pieces.iter().copied())
+            .collect();
+        self.edits.push(PieceEdit {
+            index,
+            removed,
+            inserted: pieces,
+        });
+        self.reindex();
+    }
+
+    fn reindex(&mut self) {
+        self.starts.clear();
+
+        let mut start = 0;
+        for piece in &self.pieces {
+            self.starts.push(start);
+            start += piece.length;
+        }
+        self.length = start;
+    }
+
+    /// Maps a logical offset to the piece holding it, returning the piece
+    /// index and the offset within that piece.
+    fn find(&self, offset: u64) -> Option<(usize, u64)> {
+        if offset >= self.length {
+            return None;
+        }
+
+        let index = self.starts.partition_point(|start| *start <= offset) - 1;
+        Some((index, offset - self.starts[index]))
+    }
+
+    /// Returns the source and source offset of the byte at `offset`.
+    pub fn locate(&self, offset: u64) -> Option<(PieceSource, u64)> {
+        let (index, within) = self.find(offset)?;
+        let piece = self.pieces[index];
+        Some((piece.source, piece.start + within))
+    }
+
+    /// Returns the pieces covering `length` bytes from `offset`, trimmed to
+    /// that range.
+    pub fn segments(&self, offset: u64, length: u64) -> Vec<Piece> {
+        let mut segments = vec![];
+        let Some((mut index, mut within)) = self.find(offset) else {
+            return segments;
+        };
+
+        let mut remaining = length;
+        while remaining > 0 && index < self.pieces.len() {
+            let piece = self.pieces[index];
+            let take = (piece.length - within).min(remaining);
+            segments.push(Piece {
+                source: piece.source,
+                start: piece.start + within,
+                length: take,
+            });
+
+            remaining -= take;
+            index += 1;
+            within = 0;
+        }
+
+        segments
+    }
+
+    /// Splits the piece containing `offset` so that a piece boundary falls on
+    /// it, returning the index of the first piece at or after `offset`.
+    fn split(&mut self, offset: u64) -> usize {
+        let Some((index, within)) = self.find(offset) else {
+            return self.pieces.len();
+        };
+
+        if within == 0 {
+            return index;
+        }
+
+        let piece = self.pieces[index];
+        self.splice(
+            index,
+            1,
+            vec![
+                Piece {
+                    length: within,
+                    ..piece
+                },
+                Piece {
+                    source: piece.source,
+                    start: piece.start + within,
+                    length: piece.length - within,
+                },
+            ],
+        );
+        index + 1
+    }
+
+    pub fn insert(&mut self, offset: u64, data: &[u8]) {
+        if data.is_empty() || offset > self.length {
+            return;
+        }
+
+        let added_start = self.added.len() as u64;
+        self.added.extend_from_slice(data);
+
+        let index = self.split(offset);
+        let previous = index.checked_sub(1).map(|i| self.pieces[i]);
+        match previous {
+            // Typing at the end of the last insertion just grows that piece.
+            Some(piece)
+                if piece.source == PieceSource::Added
+                    && piece.start + piece.length == added_start =>
+            {
+                let grown = Piece {
+                    length: piece.length + data.len() as u64,
+                    ..piece
+                };
+                self.splice(index - 1, 1, vec![grown]);
+            }
+            _ => self.splice(
+                index,
+                0,
+                vec![Piece {
+                    source: PieceSource::Added,
+                    start: added_start,
+                    length: data.len() as u64,
+                }],
+            ),
+        }
+    }
+
+    pub fn delete(&mut self, offset: u64, length: u64) {
+        let end = offset.saturating_add(length).min(self.length);
+        if offset >= end {
+            return;
+        }
+
+        let first = self.split(offset);
+        let last = self.split(end);
+        self.splice(first, last - first, vec![]);
+    }
+
+    /// Changes the byte at `offset` in place when it is the last one added,
+    /// returning whether it did. Only safe while the edit that added the
+    /// byte is still open, as nothing else can refer to it yet.
+    pub fn amend_last_added(&mut self, offset: u64, value: u8) -> bool {
+        match self.locate(offset) {
+            Some((PieceSource::Added, at)) if at + 1 == self.added.len() as u64 => {
+                self.added[at as usize] = value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Overwrites bytes starting at `offset` without changing the length.
+    pub fn replace(&mut self, offset: u64, data: &[u8]) {
+        let length = (data.len() as u64).min(self.length.saturating_sub(offset));
+        self.delete(offset, length);
+        self.insert(offset, &data[..length as usize]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(table: &PieceTable, original: &[u8]) -> Vec<u8> {
+        (0..table.len())
+            .map(|offset| match table.locate(offset).unwrap() {
+                (PieceSource::Original, at) => original[at as usize],
+                (PieceSource::Added, at) => table.added()[at as usize],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn insert_and_delete() {
+        let original = b"hello world";
+        let mut table = PieceTable::new(original.len() as u64);
+
+        table.insert(5, b",");
+        table.insert(12, b"!");
+        assert_eq!(read(&table, original), b"hello, world!");
+
+        table.delete(0, 7);
+        assert_eq!(read(&table, original), b"world!");
+        assert_eq!(table.len(), 6);
+        assert!(!table.is_original());
+    }
+
+    #[test]
+    fn sequential_inserts_share_a_piece() {
+        let mut table = PieceTable::new(4);
+        table.insert(2, b"a");
+        table.insert(3, b"b");
+        table.insert(4, b"c");
+
+        assert_eq!(table.pieces().len(), 3);
+    }
+
+    #[test]
+    fn replace_keeps_length() {
+        let original = b"abcd";
+        let mut table = PieceTable::new(4);
+        table.replace(2, b"xyz");
+
+        assert_eq!(read(&table, original), b"abxy");
+        assert_eq!(table.locate(1), Some((PieceSource::Original, 1)));
+        assert_eq!(table.locate(2), Some((PieceSource::Added, 0)));
+    }
+
+    #[test]
+    fn segments_are_trimmed_to_range() {
+        let mut table = PieceTable::new(10);
+        table.insert(5, b"xy");
+
+        let segments = table.segments(3, 6);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].start, 3);
+        assert_eq!(segments[0].length, 2);
+        assert_eq!(segments[1].source, PieceSource::Added);
+        assert_eq!(segments[2].length, 2);
+        assert!(table.segments(12, 4).is_empty());
+    }
+
+    #[test]
+    fn revert_restores_layout() {
+        let original = b"abcdef";
+        let mut table = PieceTable::new(6);
+        table.delete(1, 2);
+        table.insert(2, b"xy");
+        let edits = table.take_edits();
+        let edited = read(&table, original);
+
+        let redo = table.revert(edits);
+        assert!(table.is_original());
+        assert_eq!(table.len(), 6);
+
+        table.revert(redo);
+        assert_eq!(read(&table, original), edited);
+        assert!(table.take_edits().is_empty());
+    }
+}
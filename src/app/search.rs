@@ -0,0 +1,697 @@
+use std::borrow::Cow;
+use std::io;
+
+use regex_automata::meta::Regex;
+use regex_automata::util::syntax;
+use regex_automata::{Anchored, Input};
+
+use super::sedecim_file_info::SedecimFileInfo;
+
+/// Bytes scanned per read. Consecutive chunks overlap by one byte less than
+/// the longest possible match so matches spanning two chunks are still found.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Longest match a regular expression search is sure to report. Chunks are
+/// read with this much overlap, so longer matches are cut short, or missed
+/// when the pattern cannot match fewer bytes. Patterns that can match more
+/// say so in the status bar.
+pub const REGEX_MAX_MATCH: u64 = 4096;
+
+/// Most matches collected for the results list.
+pub const MAX_RESULTS: usize = 10_000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SearchKind {
+    Hex,
+    Ascii,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Regex,
+}
+
+impl SearchKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SearchKind::Hex => "HEX",
+            SearchKind::Ascii => "ASCII",
+            SearchKind::Utf8 => "UTF-8",
+            SearchKind::Utf16Le => "UTF-16LE",
+            SearchKind::Utf16Be => "UTF-16BE",
+            SearchKind::Regex => "REGEX",
+        }
+    }
+
+    pub fn next(&self) -> SearchKind {
+        match self {
+            SearchKind::Hex => SearchKind::Ascii,
+            SearchKind::Ascii => SearchKind::Utf8,
+            SearchKind::Utf8 => SearchKind::Utf16Le,
+            SearchKind::Utf16Le => SearchKind::Utf16Be,
+            SearchKind::Utf16Be => SearchKind::Regex,
+            SearchKind::Regex => SearchKind::Hex,
+        }
+    }
+
+    pub fn previous(&self) -> SearchKind {
+        match self {
+            SearchKind::Hex => SearchKind::Regex,
+            SearchKind::Ascii => SearchKind::Hex,
+            SearchKind::Utf8 => SearchKind::Ascii,
+            SearchKind::Utf16Le => SearchKind::Utf8,
+            SearchKind::Utf16Be => SearchKind::Utf16Le,
+            SearchKind::Regex => SearchKind::Utf16Be,
+        }
+    }
+}
+
+/// One byte of a search pattern. Bits cleared in `mask` are wildcards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PatternByte {
+    pub value: u8,
+    pub mask: u8,
+    pub ignore_case: bool,
+}
+
+impl PatternByte {
+    pub fn exact(value: u8) -> PatternByte {
+        PatternByte {
+            value,
+            mask: 0xff,
+            ignore_case: false,
+        }
+    }
+
+    pub fn matches(&self, byte: u8) -> bool {
+        if self.ignore_case {
+            byte.to_ascii_lowercase() == self.value
+        } else {
+            byte & self.mask == self.value
+        }
+    }
+}
+
+pub enum SearchPattern {
+    Bytes(Vec<PatternByte>),
+    /// A regular expression, with the most bytes one of its matches can
+    /// span when there is a limit.
+    Regex {
+        regex: Regex,
+        max_len: Option<u64>,
+    },
+}
+
+impl SearchPattern {
+    /// Most bytes a single match can span, or is looked for over.
+    fn max_len(&self) -> u64 {
+        match self {
+            SearchPattern::Bytes(pattern) => pattern.len() as u64,
+            SearchPattern::Regex { max_len, .. } => {
+                max_len.map_or(REGEX_MAX_MATCH, |max_len| max_len.min(REGEX_MAX_MATCH))
+            }
+        }
+    }
+
+    /// Warns when a regular expression can match more than
+    /// `REGEX_MAX_MATCH` bytes, as such matches may be cut short or missed.
+    pub fn limit_note(&self) -> Option<String> {
+        match self {
+            SearchPattern::Regex { max_len, .. }
+                if max_len.is_none_or(|max_len| max_len > REGEX_MAX_MATCH) =>
+            {
+                Some(format!(
+                    "Matches longer than {} bytes may be cut short or missed.",
+                    REGEX_MAX_MATCH
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Yields the start and length, relative to the window, of every match
+    /// starting in `from..limit`. Byte patterns report overlapping matches,
+    /// regular expressions only non-overlapping, non-empty ones.
+    fn matches<'a>(
+        &'a self,
+        window: &'a Window<'a>,
+        from: usize,
+        limit: usize,
+    ) -> Box<dyn Iterator<Item = (usize, usize)> + 'a> {
+        match self {
+            SearchPattern::Bytes(pattern) => Box::new(
+                window
+                    .bytes()
+                    .windows(pattern.len().max(1))
+                    .take(limit)
+                    .enumerate()
+                    .skip(from)
+                    .filter(|(_, bytes)| pattern_matches(pattern, bytes))
+                    .map(|(index, _)| (index, pattern.len())),
+            ),
+            SearchPattern::Regex { regex, .. } => {
+                let mut next = from;
+                Box::new(std::iter::from_fn(move || loop {
+                    if next >= limit {
+                        return None;
+                    }
+                    let found = window.find(regex, next, Anchored::No)?;
+                    if found.0 >= limit {
+                        return None;
+                    }
+                    if found.1 == 0 {
+                        next = found.0 + 1;
+                        continue;
+                    }
+                    next = found.0 + found.1;
+                    return Some(found);
+                }))
+            }
+        }
+    }
+
+    /// Start of the last match in the window starting before `limit`, the
+    /// one `find_next` would stop at last. A regular expression match can
+    /// hide others starting inside it, so those offsets are checked too.
+    fn last_match(&self, window: &Window<'_>, limit: usize) -> Option<usize> {
+        let (index, length) = self.matches(window, 0, limit).last()?;
+        let SearchPattern::Regex { regex, .. } = self else {
+            return Some(index);
+        };
+
+        (index + 1..(index + length).min(limit))
+            .rev()
+            .find(|&at| matches!(window.find(regex, at, Anchored::Yes), Some((_, 1..))))
+            .or(Some(index))
+    }
+
+    /// Where the search for further matches resumes after one at `start`
+    /// of `length` bytes.
+    fn resume_after(&self, start: u64, length: usize) -> u64 {
+        match self {
+            SearchPattern::Bytes(_) => start + 1,
+            SearchPattern::Regex { .. } => start + length as u64,
+        }
+    }
+}
+
+/// Bytes read for matching from `start`, with a byte of the file on either
+/// side where there is one, so `^`, `$` and `\b` only match where they
+/// would in the whole file. `regex::bytes` always treats the end of the
+/// bytes it is given as the end of the text, so regular expressions are run
+/// with `regex_automata`, which matches within a span and looks past it.
+/// The bytes are borrowed when the file is mapped and unedited.
+struct Window<'a> {
+    buffer: Cow<'a, [u8]>,
+    /// Index of `start` in `buffer`.
+    offset: usize,
+    /// Index in `buffer` of the end of the bytes a match may cover.
+    end: usize,
+}
+
+impl Window<'_> {
+    /// The bytes a match may cover.
+    fn bytes(&self) -> &[u8] {
+        &self.buffer[self.offset..self.end]
+    }
+
+    /// Finds the leftmost match of `regex` starting at or after `from`, as
+    /// its start and length relative to the window.
+    fn find(&self, regex: &Regex, from: usize, anchored: Anchored) -> Option<(usize, usize)> {
+        let input = Input::new(&self.buffer)
+            .span(self.offset + from..self.end)
+            .anchored(anchored);
+        regex
+            .find(input)
+            .map(|m| (m.start() - self.offset, m.len()))
+    }
+}
+
+fn pattern_matches(pattern: &[PatternByte], window: &[u8]) -> bool {
+    !pattern.is_empty() && pattern.iter().zip(window).all(|(p, b)| p.matches(*b))
+}
+
+/// Builds the pattern for `text` searched as `kind`. Ignoring case folds
+/// ASCII letters only, in every text encoding.
+pub fn build_pattern(
+    kind: SearchKind,
+    text: &str,
+    ignore_case: bool,
+) -> Result<SearchPattern, String> {
+    if text.is_empty() {
+        return Err("Search pattern is empty.".to_owned());
+    }
+
+    // Bytes that hold an ASCII letter and may be folded; the zero half of a
+    // UTF-16 code unit never is.
+    let units: Vec<(u8, bool)> = match kind {
+        SearchKind::Hex => return Ok(SearchPattern::Bytes(parse_hex_pattern(text)?)),
+        SearchKind::Regex => {
+            let config = syntax::Config::new()
+                .unicode(false)
+                .utf8(false)
+                .case_insensitive(ignore_case);
+            return Regex::builder()
+                .syntax(config)
+                .configure(Regex::config().utf8_empty(false))
+                .build(text)
+                .map(|regex| SearchPattern::Regex {
+                    regex,
+                    max_len: syntax::parse_with(text, &config)
+                        .ok()
+                        .and_then(|hir| hir.properties().maximum_len())
+                        .map(|max_len| max_len as u64),
+                })
+                .map_err(|err| {
+                    let message = match err.syntax_error() {
+                        Some(syntax_error) => syntax_error.to_string(),
+                        None => err.to_string(),
+                    };
+                    format!(
+                        "Invalid regular expression: {}",
+                        message.lines().last().unwrap_or_default()
+                    )
+                });
+        }
+        SearchKind::Ascii => {
+            if !text.is_ascii() {
+                return Err("Text is not ASCII.".to_owned());
+            }
+            text.bytes().map(|b| (b, true)).collect()
+        }
+        SearchKind::Utf8 => text.bytes().map(|b| (b, true)).collect(),
+        SearchKind::Utf16Le => text
+            .encode_utf16()
+            .flat_map(|u| {
+                let [low, high] = u.to_le_bytes();
+                [(low, high == 0), (high, false)]
+            })
+            .collect(),
+        SearchKind::Utf16Be => text
+            .encode_utf16()
+            .flat_map(|u| {
+                let [high, low] = u.to_be_bytes();
+                [(high, false), (low, high == 0)]
+            })
+            .collect(),
+    };
+
+    Ok(SearchPattern::Bytes(
+        units
+            .into_iter()
+            .map(|(value, letter)| {
+                if ignore_case && letter && value.is_ascii_alphabetic() {
+                    PatternByte {
+                        value: value.to_ascii_lowercase(),
+                        mask: 0xff,
+                        ignore_case: true,
+                    }
+                } else {
+                    PatternByte::exact(value)
+                }
+            })
+            .collect(),
+    ))
+}
+
+/// Parses a hex byte pattern such as `7f 45 4c 46` or `e8 ?? ?? ?? ?? 48 8b`,
+/// where `?` stands for any nibble.
+pub fn parse_hex_pattern(text: &str) -> Result<Vec<PatternByte>, String> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() {
+        return Err("Search pattern is empty.".to_owned());
+    }
+
+    if !digits.len().is_multiple_of(2) {
+        return Err("Search pattern needs two hex digits per byte.".to_owned());
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let mut value = 0;
+            let mut mask = 0;
+            for chr in pair {
+                value <<= 4;
+                mask <<= 4;
+                if *chr != '?' {
+                    let Some(nibble) = chr.to_digit(16) else {
+                        let byte: String = pair.iter().collect();
+                        return Err(format!("Invalid hex byte '{}'.", byte));
+                    };
+                    value |= nibble as u8;
+                    mask |= 0x0f;
+                }
+            }
+
+            Ok(PatternByte {
+                value,
+                mask,
+                ignore_case: false,
+            })
+        })
+        .collect()
+}
+
+/// Finds the first match at or after `from`, wrapping around to the start of
+/// the file. Returns the match offset and whether the search wrapped.
+pub fn find_next(
+    file_info: &SedecimFileInfo,
+    pattern: &SearchPattern,
+    from: u64,
+) -> io::Result<Option<(u64, bool)>> {
+    let from = from.min(file_info.file_size);
+    if let Some(found) = find_in_range(file_info, pattern, from, file_info.file_size)? {
+        return Ok(Some((found, false)));
+    }
+
+    Ok(find_in_range(file_info, pattern, 0, from)?.map(|found| (found, true)))
+}
+
+/// Finds the last match before `before`, wrapping around to the end of the
+/// file. Returns the match offset and whether the search wrapped.
+pub fn find_previous(
+    file_info: &SedecimFileInfo,
+    pattern: &SearchPattern,
+    before: u64,
+) -> io::Result<Option<(u64, bool)>> {
+    let before = before.min(file_info.file_size);
+    if let Some(found) = rfind_in_range(file_info, pattern, 0, before)? {
+        return Ok(Some((found, false)));
+    }
+
+    Ok(rfind_in_range(file_info, pattern, before, file_info.file_size)?.map(|found| (found, true)))
+}
+
+/// Lists the offset of every match in the file, stopping after
+/// `MAX_RESULTS`. Also returns whether the list was cut short.
+pub fn find_all(
+    file_info: &SedecimFileInfo,
+    pattern: &SearchPattern,
+) -> io::Result<(Vec<u64>, bool)> {
+    let mut results = vec![];
+    // Matches in the overlap with the previous chunk were seen there.
+    let mut resume: u64 = 0;
+    for (start, end) in file_info.mapped_ranges(0, file_info.file_size) {
+        let mut position = start;
+        while position < end {
+            let length = CHUNK_SIZE.min(end - position);
+            let window = read_window(file_info, pattern, position, length)?;
+            let from = resume.saturating_sub(position).min(length) as usize;
+            for (index, match_length) in pattern.matches(&window, from, length as usize) {
+                if results.len() == MAX_RESULTS {
+                    return Ok((results, true));
+                }
+                let found = position + index as u64;
+                results.push(found);
+                resume = pattern.resume_after(found, match_length);
+            }
+            position += length;
+        }
+    }
+
+    Ok((results, false))
+}
+
+/// Reads the bytes from `start` that may hold a match beginning before
+/// `start + length`.
+fn read_window<'a>(
+    file_info: &'a SedecimFileInfo,
+    pattern: &SearchPattern,
+    start: u64,
+    length: u64,
+) -> io::Result<Window<'a>> {
+    let end = (start + length + pattern.max_len().saturating_sub(1))
+        .min(file_info.file_size)
+        .max(start);
+    let first = start.saturating_sub(1);
+    let last = (end + 1).min(file_info.file_size).max(end);
+    let buffer = match file_info.borrow_at(first, last - first) {
+        Some(bytes) => Cow::Borrowed(bytes),
+        None => {
+            let mut buffer = vec![0; (last - first) as usize];
+            let read = file_info.read_at(first, &mut buffer)?;
+            buffer.truncate(read);
+            Cow::Owned(buffer)
+        }
+    };
+
+    let offset = ((start - first) as usize).min(buffer.len());
+    let end = ((end - first) as usize).min(buffer.len());
+    Ok(Window {
+        buffer,
+        offset,
+        end,
+    })
+}
+
+/// Finds the first match starting in `start..end`.
+fn find_in_range(
+    file_info: &SedecimFileInfo,
+    pattern: &SearchPattern,
+    start: u64,
+    end: u64,
+) -> io::Result<Option<u64>> {
+    for (start, end) in file_info.mapped_ranges(start, end) {
+        let mut position = start;
+        while position < end {
+            let length = CHUNK_SIZE.min(end - position);
+            let window = read_window(file_info, pattern, position, length)?;
+
+            if let Some((index, _)) = pattern.matches(&window, 0, length as usize).next() {
+                return Ok(Some(position + index as u64));
+            }
+            position += length;
+        }
+    }
+
+    Ok(None)
+}
+
+/// Finds the last match starting in `start..end`.
+fn rfind_in_range(
+    file_info: &SedecimFileInfo,
+    pattern: &SearchPattern,
+    start: u64,
+    end: u64,
+) -> io::Result<Option<u64>> {
+    for (start, mut end) in file_info.mapped_ranges(start, end).into_iter().rev() {
+        while end > start {
+            let length = CHUNK_SIZE.min(end - start);
+            let position = end - length;
+            let window = read_window(file_info, pattern, position, length)?;
+
+            if let Some(index) = pattern.last_match(&window, length as usize) {
+                return Ok(Some(position + index as u64));
+            }
+            end = position;
+        }
+    }
+
+    Ok(None)
+}
+
+/// Marks which of the `length` bytes from `start` belong to a match, for
+/// highlighting every hit on screen.
+pub fn highlight(
+    file_info: &SedecimFileInfo,
+    pattern: &SearchPattern,
+    start: u64,
+    length: u64,
+) -> io::Result<Vec<bool>> {
+    let mut hits = vec![false; length as usize];
+
+    let window_start = start.saturating_sub(pattern.max_len().saturating_sub(1));
+    let window_length = start + length - window_start;
+    let window = read_window(file_info, pattern, window_start, window_length)?;
+    for (index, match_length) in pattern.matches(&window, 0, window_length as usize) {
+        let match_start = window_start + index as u64;
+        let first = match_start.max(start);
+        let last = (match_start + match_length as u64).min(start + length);
+        for address in first..last {
+            hits[(address - start) as usize] = true;
+        }
+    }
+
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_files::open;
+
+    fn exact(bytes: &[u8]) -> SearchPattern {
+        SearchPattern::Bytes(bytes.iter().copied().map(PatternByte::exact).collect())
+    }
+
+    fn pattern_bytes(pattern: SearchPattern) -> Vec<PatternByte> {
+        match pattern {
+            SearchPattern::Bytes(bytes) => bytes,
+            SearchPattern::Regex { .. } => panic!("Expected a byte pattern."),
+        }
+    }
+
+    #[test]
+    fn parse_hex_pattern_accepts_spaces() {
+        let values: Vec<u8> = parse_hex_pattern("7f 45 4c46")
+            .unwrap()
+            .iter()
+            .map(|p| p.value)
+            .collect();
+
+        assert_eq!(values, vec![0x7f, 0x45, 0x4c, 0x46]);
+        assert!(parse_hex_pattern("7f 4").is_err());
+        assert!(parse_hex_pattern("zz").is_err());
+        assert!(parse_hex_pattern(" ").is_err());
+    }
+
+    #[test]
+    fn parse_hex_pattern_wildcards() {
+        let pattern = parse_hex_pattern("e8 ?? 4?").unwrap();
+
+        assert_eq!(pattern[1].mask, 0);
+        assert!(pattern_matches(&pattern, &[0xe8, 0x12, 0x4f]));
+        assert!(pattern_matches(&pattern, &[0xe8, 0xff, 0x40]));
+        assert!(!pattern_matches(&pattern, &[0xe8, 0x12, 0x5f]));
+    }
+
+    #[test]
+    fn build_pattern_encodes_text() {
+        let values = |kind, text| -> Vec<u8> {
+            pattern_bytes(build_pattern(kind, text, false).unwrap())
+                .iter()
+                .map(|p| p.value)
+                .collect()
+        };
+
+        assert_eq!(values(SearchKind::Ascii, "Ab"), b"Ab");
+        assert_eq!(values(SearchKind::Utf8, "é"), vec![0xc3, 0xa9]);
+        assert_eq!(values(SearchKind::Utf16Le, "Ab"), vec![0x41, 0, 0x62, 0]);
+        assert_eq!(values(SearchKind::Utf16Be, "Ab"), vec![0, 0x41, 0, 0x62]);
+        assert!(build_pattern(SearchKind::Ascii, "é", false).is_err());
+        assert!(build_pattern(SearchKind::Utf8, "", false).is_err());
+        assert!(build_pattern(SearchKind::Regex, "(", false).is_err());
+    }
+
+    #[test]
+    fn ignore_case_folds_ascii_letters_only() {
+        let pattern = pattern_bytes(build_pattern(SearchKind::Utf16Le, "a1", true).unwrap());
+        assert!(pattern_matches(&pattern, &[b'A', 0, b'1', 0]));
+        assert!(pattern_matches(&pattern, &[b'a', 0, b'1', 0]));
+        assert!(!pattern_matches(&pattern, &[b'A', 0x20, b'1', 0]));
+
+        let pattern = pattern_bytes(build_pattern(SearchKind::Ascii, "a", false).unwrap());
+        assert!(!pattern_matches(&pattern, b"A"));
+    }
+
+    #[test]
+    fn highlight_marks_partial_matches() {
+        let (info, _file) = open(b"xxabcxxabc");
+        let hits = highlight(&info, &exact(b"abc"), 3, 5).unwrap();
+
+        assert_eq!(hits, vec![true, true, false, false, true]);
+    }
+
+    #[test]
+    fn find_across_chunk_boundary() {
+        let mut data = vec![0; (CHUNK_SIZE * 2) as usize];
+        let at = CHUNK_SIZE as usize - 2;
+        data[at..at + 4].copy_from_slice(&[0x7f, 0x45, 0x4c, 0x46]);
+        let (info, _file) = open(&data);
+        let pattern = exact(&[0x7f, 0x45, 0x4c, 0x46]);
+        let regex = build_pattern(SearchKind::Regex, r"\x7fE[L]F", false).unwrap();
+
+        assert_eq!(
+            find_next(&info, &pattern, 0).unwrap(),
+            Some((at as u64, false))
+        );
+        assert_eq!(
+            find_previous(&info, &pattern, CHUNK_SIZE * 2).unwrap(),
+            Some((at as u64, false))
+        );
+        assert_eq!(
+            find_next(&info, &regex, 0).unwrap(),
+            Some((at as u64, false))
+        );
+        assert_eq!(find_all(&info, &regex).unwrap(), (vec![at as u64], false));
+    }
+
+    #[test]
+    fn regex_matches_as_in_the_whole_file() {
+        let chunk = CHUNK_SIZE as usize;
+        let mut data = vec![0; chunk * 3];
+        data[chunk - 1] = 1;
+        data[chunk * 2 - 10..chunk * 2 + 10].fill(2);
+        let (info, _file) = open(&data);
+        let regex = |text| build_pattern(SearchKind::Regex, text, false).unwrap();
+
+        assert_eq!(find_all(&info, &regex(r"^\x00")).unwrap(), (vec![0], false));
+        assert_eq!(
+            find_all(&info, &regex(r"\x01[^\x01]{4095}$")).unwrap(),
+            (vec![], false)
+        );
+        assert_eq!(
+            find_all(&info, &regex(r"\x02+")).unwrap(),
+            (vec![(chunk * 2 - 10) as u64], false)
+        );
+        assert_eq!(
+            find_previous(&info, &regex(r"\x02\x02"), (chunk * 2) as u64).unwrap(),
+            Some(((chunk * 2 - 1) as u64, false))
+        );
+    }
+
+    #[test]
+    fn regex_matches_up_to_the_limit_across_chunks() {
+        let chunk = CHUNK_SIZE as usize;
+        let at = chunk - 100;
+        let mut data = vec![b'x'; chunk * 2];
+        data[at..at + 2].copy_from_slice(b"MZ");
+        let end = at + REGEX_MAX_MATCH as usize;
+        data[end - 2..end].copy_from_slice(b"PE");
+        let (info, _file) = open(&data);
+        let regex = |text| build_pattern(SearchKind::Regex, text, false).unwrap();
+
+        let longest = regex(r"MZ.{4092}PE");
+        assert!(longest.limit_note().is_none());
+        assert_eq!(find_all(&info, &longest).unwrap(), (vec![at as u64], false));
+        assert_eq!(
+            find_previous(&info, &longest, (chunk * 2) as u64).unwrap(),
+            Some((at as u64, false))
+        );
+        let hits = highlight(&info, &longest, end as u64 - 4, 8).unwrap();
+        assert_eq!(hits, [true, true, true, true, false, false, false, false]);
+
+        // Patterns that can match more than that are flagged.
+        let longer = regex(r"MZ.{4093}PE");
+        assert!(longer.limit_note().is_some());
+        assert!(regex(r"MZ.*PE").limit_note().is_some());
+    }
+
+    #[test]
+    fn find_wraps_around() {
+        let (info, _file) = open(b"abcabc");
+
+        assert_eq!(
+            find_next(&info, &exact(b"bc"), 2).unwrap(),
+            Some((4, false))
+        );
+        assert_eq!(find_next(&info, &exact(b"bc"), 5).unwrap(), Some((1, true)));
+        assert_eq!(
+            find_previous(&info, &exact(b"bc"), 4).unwrap(),
+            Some((1, false))
+        );
+        assert_eq!(
+            find_previous(&info, &exact(b"bc"), 1).unwrap(),
+            Some((4, true))
+        );
+        assert_eq!(find_next(&info, &exact(b"zz"), 0).unwrap(), None);
+    }
+
+    #[test]
+    fn find_all_lists_wildcard_matches() {
+        let (info, _file) = open(&[0xe8, 1, 2, 0x48, 0xe8, 3, 4, 0x48, 0xe8]);
+        let pattern = SearchPattern::Bytes(parse_hex_pattern("e8 ?? ?? 48").unwrap());
+
+        assert_eq!(find_all(&info, &pattern).unwrap(), (vec![0, 4], false));
+    }
+}
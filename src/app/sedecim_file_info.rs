@@ -1,14 +1,21 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
-use std::io::{Seek, SeekFrom};
-use std::rc::Rc;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
 
+use super::byte_source::{self, BackendKind, ByteSource, MemoryRegion, ProcessSource, STDIN_NAME};
+use super::edit_journal::{ByteChange, EditJournal};
+use super::error::SedecimError;
+use super::page_cache::{PageCache, Prefetcher, CACHE_PAGES};
+use super::piece_table::{Piece, PieceEdit, PieceSource, PieceTable};
 use super::sedecim_file_page::SedecimFilePage;
 
 pub const BUFFER_SIZE: usize = 250;
 pub const BUFFER_SIZE_U64: u64 = 250;
-pub const LINE_SIZE: u64 = 10;
+pub const DEFAULT_LINE_SIZE: u64 = 16;
+pub const DEFAULT_VISIBLE_LINES: u64 = 20;
+/// Largest file read into memory whole, such as when patching.
+pub const MAX_LOAD_SIZE: u64 = 512 * 1024 * 1024;
 
 pub enum MoveValues {
     UpLine,
@@ -20,88 +27,1221 @@ pub struct SedecimFileInfo {
     pub file_name: String,
     pub file_offset: u64,
     pub file_size: u64,
-    pages: HashMap<u64, Rc<SedecimFilePage>>,
+    pub line_size: u64,
+    pub visible_lines: u64,
+    /// Size of the file on disk, which the original pieces refer to.
+    original_size: u64,
+    source: Arc<dyn ByteSource>,
+    /// How the file is reopened after it is saved.
+    backend_kind: BackendKind,
+    pages: PageCache,
+    /// Reads the pages around the view ahead of time.
+    prefetcher: Prefetcher,
+    table: PieceTable,
+    journal: EditJournal,
+    /// Counts changes to the contents, so anything worked out from them
+    /// can tell when it is out of date.
+    revision: u64,
 }
 
 impl SedecimFileInfo {
-    pub fn new(file_name: String) -> SedecimFileInfo {
+    pub fn new(file_name: String) -> Result<SedecimFileInfo, SedecimError> {
+        SedecimFileInfo::open(file_name, BackendKind::default())
+    }
+
+    pub fn open(
+        file_name: String,
+        backend_kind: BackendKind,
+    ) -> Result<SedecimFileInfo, SedecimError> {
+        match byte_source::open(&file_name, backend_kind) {
+            Ok(source) => Ok(SedecimFileInfo::with_source(
+                file_name,
+                source,
+                backend_kind,
+            )),
+            Err(source) => Err(SedecimError::Open {
+                path: file_name,
+                source,
+            }),
+        }
+    }
+
+    /// Views the memory of the running process `pid`, at its virtual
+    /// addresses.
+    pub fn open_process(pid: u32) -> Result<SedecimFileInfo, SedecimError> {
+        let file_name = format!("/proc/{}/mem", pid);
+        match ProcessSource::open(pid) {
+            Ok(source) => Ok(SedecimFileInfo::with_source(
+                file_name,
+                Box::new(source),
+                BackendKind::Read,
+            )),
+            Err(source) => Err(SedecimError::Open {
+                path: file_name,
+                source,
+            }),
+        }
+    }
+
+    /// Views the bytes of `source`, shown under `file_name`.
+    pub fn with_source(
+        file_name: String,
+        source: Box<dyn ByteSource>,
+        backend_kind: BackendKind,
+    ) -> SedecimFileInfo {
+        let source: Arc<dyn ByteSource> = source.into();
         let file_offset: u64 = 0;
-        let file_size: u64 = 0;
-        let pages = HashMap::new();
+        let file_size = source.len();
+        let original_size = file_size;
+        let line_size = DEFAULT_LINE_SIZE;
+        let visible_lines = DEFAULT_VISIBLE_LINES;
+        let pages = PageCache::new(CACHE_PAGES);
+        let prefetcher = Prefetcher::new(source.clone());
+        let table = PieceTable::new(file_size);
+        let journal = EditJournal::new();
+        let revision = 0;
 
         SedecimFileInfo {
             file_name,
             file_offset,
             file_size,
+            line_size,
+            visible_lines,
+            original_size,
+            source,
+            backend_kind,
             pages,
+            prefetcher,
+            table,
+            journal,
+            revision,
+        }
+    }
+
+    /// False when the data has no file to save back to, such as piped input.
+    pub fn has_path(&self) -> bool {
+        self.source.has_path()
+    }
+
+    /// Tag for the kind of source, such as "PIPE", shown in the status bar.
+    pub fn source_label(&self) -> Option<&'static str> {
+        self.source.label()
+    }
+
+    /// The mapped regions of process memory, empty for other sources.
+    pub fn regions(&self) -> &[MemoryRegion] {
+        self.source.regions()
+    }
+
+    /// False for process memory outside every readable region, which is
+    /// drawn as a gap.
+    pub fn is_mapped(&self, address: u64) -> bool {
+        let regions = self.source.regions();
+        if regions.is_empty() {
+            return true;
+        }
+
+        let index = regions.partition_point(|region| region.end <= address);
+        regions
+            .get(index)
+            .is_some_and(|region| region.start <= address && region.readable())
+    }
+
+    /// The parts of `start..end` that hold data, so scans can skip the gaps
+    /// in process memory. Adjacent regions are joined.
+    pub fn mapped_ranges(&self, start: u64, end: u64) -> Vec<(u64, u64)> {
+        let regions = self.source.regions();
+        if regions.is_empty() {
+            return vec![(start, end)];
+        }
+
+        let mut ranges: Vec<(u64, u64)> = vec![];
+        for region in regions.iter().filter(|region| region.readable()) {
+            let from = region.start.max(start);
+            let to = region.end.min(end);
+            if from >= to {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some(last) if last.1 == from => last.1 = to,
+                _ => ranges.push((from, to)),
+            }
+        }
+        ranges
+    }
+
+    /// Name to show for the data being viewed.
+    pub fn display_name(&self) -> &str {
+        if self.file_name == STDIN_NAME {
+            "stdin"
+        } else {
+            &self.file_name
         }
     }
 
+    /// Moves the view to `address`. Pages are read when first drawn, while
+    /// the ones around the new view are read in the background.
     pub fn set_address(&mut self, address: u64) {
         self.file_offset = address;
-        self.read_bytes(address);
+        self.prefetch();
     }
 
-    fn read_bytes(&mut self, load_address: u64) {
-        let current_page_number = SedecimFilePage::get_page(load_address);
-
-        if !self.pages.contains_key(&current_page_number) {
-            let mut buffer: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
-            let mut file = File::open(&self.file_name).unwrap();
+    /// Asks for the original pages from one screen before the view to two
+    /// screens after it, so paging in either direction finds them cached.
+    fn prefetch(&mut self) {
+        self.prefetcher.receive(&mut self.pages);
+        // Data already in memory needs no page cache, and live data would be
+        // out of date by the time it is drawn.
+        if self.source.as_slice().is_some() || self.source.is_live() {
+            return;
+        }
 
-            self.file_size = std::fs::metadata(&self.file_name).unwrap().len();
-            let read = file.by_ref();
-            let _ = read.seek(SeekFrom::Start(self.file_offset)).unwrap();
-            let _ = read.take(BUFFER_SIZE_U64).read(&mut buffer).unwrap();
+        let page_size = self.page_size();
+        let start = self.file_offset.saturating_sub(page_size);
+        let segments = self.table.segments(start, page_size * 4);
+        for segment in segments {
+            if segment.source != PieceSource::Original || segment.length == 0 {
+                continue;
+            }
+            let first = SedecimFilePage::get_page(segment.start);
+            let last = SedecimFilePage::get_page(segment.start + segment.length - 1);
+            for page_id in first..=last {
+                if !self.pages.contains(page_id) {
+                    self.prefetcher.request(page_id);
+                }
+            }
+        }
+    }
 
-            let mut page = SedecimFilePage::new();
-            page.loaded = true;
-            page.page_id = current_page_number;
-            page.buffer = buffer;
-            page.page_start = load_address - (load_address % BUFFER_SIZE_U64);
+    /// True when the bytes can change while they are viewed, such as
+    /// process memory.
+    pub fn is_live(&self) -> bool {
+        self.source.is_live()
+    }
 
-            self.pages.insert(current_page_number, Rc::new(page));
+    /// Forgets the pages read from a live source such as process memory, so
+    /// the next draw shows its current contents. Within one draw the pages
+    /// are still shared.
+    pub fn refresh(&mut self) {
+        if self.source.is_live() {
+            self.pages.clear();
         }
     }
 
-    pub fn get_page(&mut self, address: u64) -> Rc<SedecimFilePage> {
-        self.read_bytes(address);
+    /// Sets how many bytes are shown per line and how many lines fit on
+    /// screen, moving the view back to the start of the line it is on.
+    pub fn set_layout(&mut self, line_size: u64, visible_lines: u64) {
+        self.line_size = line_size.max(1);
+        self.visible_lines = visible_lines.max(1);
+        self.set_address(self.file_offset - self.file_offset % self.line_size);
+    }
 
-        match self.pages.get(&SedecimFilePage::get_page(address)) {
-            Some(page) => return page.clone(),
-            None => panic!("Page failed to Load."),
+    /// Number of bytes shown on one screen.
+    pub fn page_size(&self) -> u64 {
+        self.line_size * self.visible_lines
+    }
+
+    /// Loads the page of the original file holding `load_address`, unless
+    /// it is cached or has been prefetched.
+    fn read_bytes(&mut self, load_address: u64) -> Result<Arc<SedecimFilePage>, SedecimError> {
+        let current_page_number = SedecimFilePage::get_page(load_address);
+        self.prefetcher.receive(&mut self.pages);
+        if let Some(page) = self.pages.get(current_page_number) {
+            return Ok(page);
         }
+
+        let page = SedecimFilePage::load(&*self.source, current_page_number).map_err(|err| {
+            SedecimError::read(
+                self.display_name(),
+                current_page_number * BUFFER_SIZE_U64,
+                err,
+            )
+        })?;
+
+        let page = Arc::new(page);
+        self.pages.insert(page.clone());
+        Ok(page)
     }
 
-    pub fn scroll(&mut self, scroll_amount: MoveValues) {
-        match scroll_amount {
-            MoveValues::UpLine => {
-                if self.file_offset >= LINE_SIZE {
-                    self.set_address(self.file_offset - LINE_SIZE);
-                } else {
-                    self.set_address(0);
+    /// Fills `buffer` from `offset` in the data as it was opened, before any
+    /// edits.
+    fn read_original(&self, offset: u64, buffer: &mut [u8]) -> Result<(), SedecimError> {
+        self.source
+            .read_at(offset, buffer)
+            .map_err(|err| SedecimError::read(self.display_name(), offset, err))
+    }
+
+    /// Returns the page of the original, unedited file holding `address`.
+    pub fn get_page(&mut self, address: u64) -> Result<Arc<SedecimFilePage>, SedecimError> {
+        self.read_bytes(address)
+    }
+
+    /// Returns the byte at `address` with all edits applied, or `None` when
+    /// the address is past the end of the file.
+    pub fn get_byte(&mut self, address: u64) -> Result<Option<u8>, SedecimError> {
+        match self.table.locate(address) {
+            Some((PieceSource::Original, original_address)) => {
+                // Data already in memory needs no page cache.
+                let data = self.source.as_slice();
+                if let Some(value) = data.and_then(|data| data.get(original_address as usize)) {
+                    return Ok(Some(*value));
                 }
+
+                let page = self.get_page(original_address)?;
+                Ok(Some(
+                    page.buffer[(original_address - page.page_start) as usize],
+                ))
             }
+            Some((PieceSource::Added, added_address)) => {
+                Ok(Some(self.table.added()[added_address as usize]))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Reads bytes starting at `address` with all edits applied, returning
+    /// how many were read. The data is read straight from the file rather
+    /// than through the page cache, so scanning a large file does not fill
+    /// the cache.
+    pub fn read_at(&self, address: u64, buffer: &mut [u8]) -> Result<usize, SedecimError> {
+        let mut filled = 0;
 
-            MoveValues::DownLine => {
-                if self.file_offset <= self.file_size - LINE_SIZE {
-                    self.set_address(self.file_offset + LINE_SIZE);
+        for segment in self.table.segments(address, buffer.len() as u64) {
+            let target = &mut buffer[filled..filled + segment.length as usize];
+            match segment.source {
+                PieceSource::Original => self.read_original(segment.start, target)?,
+                PieceSource::Added => {
+                    let start = segment.start as usize;
+                    target.copy_from_slice(&self.table.added()[start..start + target.len()]);
                 }
             }
+            filled += target.len();
+        }
+
+        Ok(filled)
+    }
+
+    /// Borrows up to `length` bytes from `address` straight from a source
+    /// already in memory, such as a mapped file, when none of them has been
+    /// edited. `None` means they have to be copied out with `read_at`.
+    pub fn borrow_at(&self, address: u64, length: u64) -> Option<&[u8]> {
+        let data = self.source.as_slice()?;
+        match self.table.segments(address, length)[..] {
+            [Piece {
+                source: PieceSource::Original,
+                start,
+                length,
+            }] => data.get(start as usize..(start + length) as usize),
+            _ => None,
+        }
+    }
+
+    /// Reads the whole file, with all edits applied, into memory.
+    pub fn load(&self) -> Result<Vec<u8>, SedecimError> {
+        let mut data = vec![0; self.checked_load_size(self.file_size)?];
+        let read = self.read_at(0, &mut data)?;
+        data.truncate(read);
+        Ok(data)
+    }
+
+    /// Reads the file as it was opened or last saved, without the pending
+    /// edits, into memory.
+    pub fn load_original(&self) -> Result<Vec<u8>, SedecimError> {
+        let mut data = vec![0; self.checked_load_size(self.original_size)?];
+        self.read_original(0, &mut data)?;
+        Ok(data)
+    }
+
+    /// Size of the file as it was opened or last saved.
+    pub fn original_size(&self) -> u64 {
+        self.original_size
+    }
+
+    fn checked_load_size(&self, size: u64) -> Result<usize, SedecimError> {
+        if size > MAX_LOAD_SIZE {
+            return Err(SedecimError::TooLarge {
+                path: self.display_name().to_owned(),
+                size,
+                limit: MAX_LOAD_SIZE >> 20,
+            });
+        }
+        Ok(size as usize)
+    }
+
+    /// Overwrites the byte at `address`. The change is kept in memory until
+    /// saved and recorded in the edit journal so it can be undone.
+    pub fn set_byte(&mut self, address: u64, value: u8) -> Result<(), SedecimError> {
+        self.set_bytes(address, &[value])
+    }
+
+    /// Overwrites bytes from `address` as a single undo step. Bytes past the
+    /// end of the file are ignored.
+    pub fn set_bytes(&mut self, address: u64, values: &[u8]) -> Result<(), SedecimError> {
+        let length = (values.len() as u64).min(self.file_size.saturating_sub(address)) as usize;
+        let mut old_values = vec![0; length];
+        self.read_at(address, &mut old_values)?;
+        if old_values == values[..length] {
+            return Ok(());
+        }
+
+        self.begin_edit_group();
+        for (index, (old_value, value)) in old_values.iter().zip(values).enumerate() {
+            if old_value != value {
+                self.journal
+                    .record(address + index as u64, Some(*old_value), Some(*value));
+            }
+        }
+        self.table.replace(address, &values[..length]);
+        self.end_edit_group();
+        Ok(())
+    }
+
+    /// Inserts a new byte before `address`, shifting the rest of the file.
+    pub fn insert_byte(&mut self, address: u64, value: u8) {
+        self.insert_bytes(address, &[value]);
+    }
+
+    /// Inserts bytes before `address` as a single undo step.
+    pub fn insert_bytes(&mut self, address: u64, values: &[u8]) {
+        if address > self.file_size || values.is_empty() {
+            return;
+        }
+
+        self.begin_edit_group();
+        for (index, value) in values.iter().enumerate() {
+            self.journal
+                .record(address + index as u64, None, Some(*value));
+        }
+        self.table.insert(address, values);
+        self.end_edit_group();
+        self.file_size = self.table.len();
+    }
+
+    /// Sets the byte the open edit group inserted at `address`, such as when
+    /// the low nibble follows the high one in insert mode. The byte is
+    /// written in place, so bytes typed one after another stay in one piece.
+    pub fn amend_inserted_byte(&mut self, address: u64, value: u8) -> Result<(), SedecimError> {
+        let Some(old_value) = self.get_byte(address)? else {
+            return Ok(());
+        };
+        if !self.table.amend_last_added(address, value) {
+            return self.set_byte(address, value);
+        }
+
+        self.journal.record(address, Some(old_value), Some(value));
+        self.revision += 1;
+        Ok(())
+    }
+
+    /// Removes the byte at `address`, shifting the rest of the file.
+    pub fn delete_byte(&mut self, address: u64) -> Result<(), SedecimError> {
+        let Some(old_value) = self.get_byte(address)? else {
+            return Ok(());
+        };
+
+        self.begin_edit_group();
+        self.journal.record(address, Some(old_value), None);
+        self.table.delete(address, 1);
+        self.end_edit_group();
+        self.file_size = self.table.len();
+        Ok(())
+    }
+
+    /// Removes `length` bytes from `address` as a single undo step.
+    pub fn delete_bytes(&mut self, address: u64, length: u64) -> Result<(), SedecimError> {
+        let length = length.min(self.file_size.saturating_sub(address));
+        if length == 0 {
+            return Ok(());
+        }
+        let mut old_values = vec![0; length as usize];
+        self.read_at(address, &mut old_values)?;
+
+        self.begin_edit_group();
+        for (index, old_value) in old_values.iter().enumerate() {
+            self.journal
+                .record(address + index as u64, Some(*old_value), None);
+        }
+        self.table.delete(address, length);
+        self.end_edit_group();
+        self.file_size = self.table.len();
+        Ok(())
+    }
+
+    /// Stops keeping an undo history, for edits saved straight away whose
+    /// history would only take up memory. Saving starts a new one.
+    pub fn disable_history(&mut self) {
+        self.journal.disable();
+    }
+
+    pub fn keeps_history(&self) -> bool {
+        self.journal.is_enabled()
+    }
+
+    pub fn begin_edit_group(&mut self) {
+        self.journal.begin_group();
+    }
+
+    pub fn end_edit_group(&mut self) {
+        self.journal.record_pieces(self.table.take_edits());
+        self.journal.end_group();
+        self.revision += 1;
+    }
+
+    /// Changes whenever the contents do, through edits, undo or redo.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Reverts the most recent group of changes, returning it.
+    pub fn undo(&mut self) -> Option<&[ByteChange]> {
+        let mut group = self.journal.pop_undo()?;
+        group.pieces = self.revert_pieces(group.pieces);
+
+        Some(self.journal.push_redo(group))
+    }
+
+    /// Reapplies the most recently undone group of changes, returning it.
+    pub fn redo(&mut self) -> Option<&[ByteChange]> {
+        let mut group = self.journal.pop_redo()?;
+        group.pieces = self.revert_pieces(group.pieces);
+
+        Some(self.journal.push_undo(group))
+    }
+
+    /// Reverts a group's changes to the piece list, returning the changes
+    /// that apply them again.
+    fn revert_pieces(&mut self, edits: Vec<PieceEdit>) -> Vec<PieceEdit> {
+        let reverted = self.table.revert(edits);
+        self.file_size = self.table.len();
+        self.revision += 1;
+        reverted
+    }
+
+    /// True when the byte at `address` was typed in rather than read from
+    /// the file.
+    pub fn is_modified(&self, address: u64) -> bool {
+        matches!(self.table.locate(address), Some((PieceSource::Added, _)))
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.table.is_original()
+    }
+
+    /// Writes the pending modifications back to the file being viewed.
+    pub fn save(&mut self) -> Result<(), SedecimError> {
+        self.check_whole_write()?;
+        if !self.has_path() {
+            return Err(SedecimError::Write {
+                path: self.display_name().to_owned(),
+                source: io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                        "{} data has no file to save back to, use save as",
+                        self.source_label().unwrap_or("this")
+                    ),
+                ),
+            });
+        }
+        if self.source.saves_in_place() {
+            return self.save_in_place();
+        }
+        let path = self.file_name.clone();
+        self.save_as(&path)
+    }
+
+    /// Writes the changed bytes back into the source, for sources such as
+    /// devices that cannot be replaced by a new file. Their size is fixed,
+    /// so only overwritten bytes can be saved this way.
+    fn save_in_place(&mut self) -> Result<(), SedecimError> {
+        let mut position = 0;
+        let mut writes = vec![];
+        for piece in self.table.pieces() {
+            if piece.source == PieceSource::Original && piece.start != position {
+                return Err(SedecimError::Write {
+                    path: self.file_name.clone(),
+                    source: io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "inserted or deleted bytes cannot be saved in place",
+                    ),
+                });
+            }
+            if piece.source == PieceSource::Added {
+                writes.push((position, piece.start as usize, piece.length as usize));
+            }
+            position += piece.length;
+        }
+        if position != self.original_size {
+            return Err(SedecimError::Write {
+                path: self.file_name.clone(),
+                source: io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "the size cannot change when saving in place",
+                ),
+            });
+        }
+
+        for (position, start, length) in writes {
+            self.source
+                .write_at(position, &self.table.added()[start..start + length])
+                .map_err(|source| SedecimError::Write {
+                    path: self.file_name.clone(),
+                    source,
+                })?;
+        }
+
+        self.table = PieceTable::new(self.file_size);
+        self.journal = EditJournal::new();
+        // Pages still being read may hold the old bytes.
+        self.pages.clear();
+        self.prefetcher = Prefetcher::new(self.source.clone());
+        Ok(())
+    }
+
+    /// Writes the file with all pending modifications to `path`.
+    ///
+    /// The data goes to a temporary file next to the target first, which is
+    /// then renamed over it, so a failed write never leaves a half written
+    /// file behind. On success `path` becomes the file being viewed and the
+    /// edit history is cleared, as it refers to the previous file contents.
+    pub fn save_as(&mut self, path: &str) -> Result<(), SedecimError> {
+        self.check_whole_write()?;
+        let target = Path::new(path);
+        let target_name = target.file_name().ok_or_else(|| SedecimError::Write {
+            path: path.to_owned(),
+            source: io::Error::new(io::ErrorKind::InvalidInput, "Save path has no file name."),
+        })?;
+        let temp_path =
+            target.with_file_name(format!(".{}.sedecim-tmp", target_name.to_string_lossy()));
+
+        let result = self.write_to(&temp_path).and_then(|_| {
+            fs::rename(&temp_path, target).map_err(|source| SedecimError::Write {
+                path: path.to_owned(),
+                source,
+            })
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result?;
+
+        // Read the saved file from now on, as the edits refer to it.
+        let source =
+            byte_source::open(path, self.backend_kind).map_err(|source| SedecimError::Open {
+                path: path.to_owned(),
+                source,
+            })?;
+        self.source = source.into();
+        self.prefetcher = Prefetcher::new(self.source.clone());
+        self.file_name = path.to_owned();
+        self.original_size = self.file_size;
+        self.table = PieceTable::new(self.file_size);
+        self.journal = EditJournal::new();
+        self.pages.clear();
+
+        Ok(())
+    }
+
+    /// Fails for sources that cannot be written out whole, such as process
+    /// memory.
+    fn check_whole_write(&self) -> Result<(), SedecimError> {
+        if self.source.can_write_whole() {
+            return Ok(());
+        }
+        Err(SedecimError::Write {
+            path: self.display_name().to_owned(),
+            source: io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "{} data cannot be saved whole, export a selection instead",
+                    self.source_label().unwrap_or("this")
+                ),
+            ),
+        })
+    }
 
-            MoveValues::UpPage => {
-                if self.file_offset >= BUFFER_SIZE_U64 {
-                    self.set_address(self.file_offset - BUFFER_SIZE_U64);
-                } else {
-                    self.set_address(0);
+    /// Fails when `path` is the file being viewed. Writing over it would cut
+    /// it short while it is still read, which kills the process when it is
+    /// mapped. Saving is the exception, as it renames a new file over it.
+    pub fn check_output_path(&self, path: &str) -> Result<(), SedecimError> {
+        if !self.has_path() || !same_file(Path::new(path), Path::new(&self.file_name)) {
+            return Ok(());
+        }
+        Err(SedecimError::Write {
+            path: path.to_owned(),
+            source: io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "this is the file being viewed, save it or write to another file",
+            ),
+        })
+    }
+
+    /// Writes `length` bytes from `start`, with all edits applied, to a new
+    /// file at `path`.
+    pub fn write_range(&self, start: u64, length: u64, path: &str) -> Result<(), SedecimError> {
+        self.check_output_path(path)?;
+        let write_error = |source| SedecimError::Write {
+            path: path.to_owned(),
+            source,
+        };
+        let mut offset = start;
+        let end = start.saturating_add(length).min(self.file_size);
+        if !self.source.can_write_whole() && end.saturating_sub(start) > MAX_LOAD_SIZE {
+            return Err(write_error(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "{} data is exported at most {} MiB at a time",
+                    self.source_label().unwrap_or("this"),
+                    MAX_LOAD_SIZE >> 20
+                ),
+            )));
+        }
+        let mut output = File::create(path).map_err(write_error)?;
+        let mut buffer = vec![0; 64 * 1024];
+
+        while offset < end {
+            let wanted = ((end - offset) as usize).min(buffer.len());
+            let read = self.read_at(offset, &mut buffer[..wanted])?;
+            if read == 0 {
+                break;
+            }
+
+            output.write_all(&buffer[..read]).map_err(write_error)?;
+            offset += read as u64;
+        }
+
+        output.sync_all().map_err(write_error)
+    }
+
+    /// Streams the pieces out to `path` without loading the whole file.
+    fn write_to(&self, path: &Path) -> Result<(), SedecimError> {
+        let write_error = |source| SedecimError::Write {
+            path: path.to_string_lossy().into_owned(),
+            source,
+        };
+        let mut output = File::create(path).map_err(write_error)?;
+        if self.has_path() {
+            fs::metadata(&self.file_name)
+                .and_then(|metadata| output.set_permissions(metadata.permissions()))
+                .map_err(write_error)?;
+        }
+
+        let mut buffer = vec![0; 64 * 1024];
+        for piece in self.table.pieces() {
+            let start = piece.start as usize;
+            let end = (piece.start + piece.length) as usize;
+            match piece.source {
+                PieceSource::Original => {
+                    let mut offset = piece.start;
+                    while offset < piece.start + piece.length {
+                        let wanted =
+                            ((piece.start + piece.length - offset) as usize).min(buffer.len());
+                        self.read_original(offset, &mut buffer[..wanted])?;
+                        output.write_all(&buffer[..wanted]).map_err(write_error)?;
+                        offset += wanted as u64;
+                    }
                 }
+                PieceSource::Added => output
+                    .write_all(&self.table.added()[start..end])
+                    .map_err(write_error)?,
+            }
+        }
+
+        output.sync_all().map_err(write_error)
+    }
+
+    pub fn scroll(&mut self, scroll_amount: MoveValues) {
+        let amount = match scroll_amount {
+            MoveValues::UpLine | MoveValues::DownLine => self.line_size,
+            MoveValues::UpPage | MoveValues::DownPage => self.page_size(),
+        };
+
+        match scroll_amount {
+            MoveValues::UpLine | MoveValues::UpPage => {
+                self.set_address(self.file_offset.saturating_sub(amount));
             }
 
-            MoveValues::DownPage => {
-                if self.file_offset <= self.file_size - BUFFER_SIZE_U64 {
-                    self.set_address(self.file_offset + BUFFER_SIZE_U64);
+            MoveValues::DownLine | MoveValues::DownPage => {
+                if self.file_offset + amount <= self.file_size {
+                    self.set_address(self.file_offset + amount);
                 }
             }
         };
     }
 }
+
+/// True when both paths lead to the same existing file.
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_files::temp_file;
+    use tempfile::NamedTempFile;
+
+    fn open(file: &NamedTempFile) -> SedecimFileInfo {
+        let mut info = SedecimFileInfo::new(file.path().to_string_lossy().to_string()).unwrap();
+        info.set_address(0);
+        info
+    }
+
+    #[test]
+    fn get_byte_reads_across_pages() {
+        let data: Vec<u8> = (0..600).map(|i| (i % 256) as u8).collect();
+        let file = temp_file(&data);
+        let mut info = open(&file);
+
+        info.set_address(10);
+        assert_eq!(info.get_byte(260).unwrap(), Some(4));
+        assert_eq!(info.get_byte(599).unwrap(), Some(87));
+        assert_eq!(info.get_byte(600).unwrap(), None);
+    }
+
+    #[test]
+    fn set_byte_tracks_modifications() {
+        let file = temp_file(&[1, 2, 3]);
+        let mut info = open(&file);
+
+        info.set_byte(1, 0xff).unwrap();
+        assert_eq!(info.get_byte(1).unwrap(), Some(0xff));
+        assert!(info.is_modified(1));
+        assert!(info.has_changes());
+
+        assert!(!info.is_modified(0));
+
+        info.undo();
+        assert!(!info.is_modified(1));
+        assert!(!info.has_changes());
+
+        info.set_byte(3, 0xff).unwrap();
+        assert!(!info.has_changes());
+    }
+
+    #[test]
+    fn undo_and_redo_groups() {
+        let file = temp_file(&[1, 2, 3, 4]);
+        let mut info = open(&file);
+
+        info.set_byte(0, 9).unwrap();
+        info.begin_edit_group();
+        info.set_byte(1, 7).unwrap();
+        info.set_byte(2, 7).unwrap();
+        info.set_byte(3, 7).unwrap();
+        info.end_edit_group();
+
+        assert_eq!(info.undo().unwrap().len(), 3);
+        assert_eq!(info.get_byte(1).unwrap(), Some(2));
+        assert_eq!(info.get_byte(3).unwrap(), Some(4));
+        assert_eq!(info.get_byte(0).unwrap(), Some(9));
+
+        assert_eq!(info.undo().unwrap()[0].offset, 0);
+        assert!(!info.has_changes());
+        assert!(info.undo().is_none());
+
+        info.redo();
+        info.redo();
+        assert_eq!(info.get_byte(0).unwrap(), Some(9));
+        assert_eq!(info.get_byte(2).unwrap(), Some(7));
+        assert!(info.redo().is_none());
+
+        let revision = info.revision();
+        info.undo();
+        assert_ne!(info.revision(), revision);
+    }
+
+    #[test]
+    fn typing_inserted_bytes_keeps_one_piece() {
+        let file = temp_file(&[1, 2, 3, 4]);
+        let mut info = open(&file);
+
+        // Each byte is typed as in insert mode, one nibble at a time.
+        for address in 2..102 {
+            info.begin_edit_group();
+            info.insert_byte(address, 0xa0);
+            info.amend_inserted_byte(address, 0xab).unwrap();
+            info.end_edit_group();
+        }
+        assert_eq!(info.table.pieces().len(), 3);
+        assert_eq!(info.file_size, 104);
+        assert_eq!(info.get_byte(101).unwrap(), Some(0xab));
+
+        assert_eq!(info.undo().unwrap().len(), 2);
+        assert_eq!(info.get_byte(101).unwrap(), Some(3));
+        info.redo();
+        assert_eq!(info.get_byte(101).unwrap(), Some(0xab));
+    }
+
+    #[test]
+    fn insert_and_delete_shift_the_file() {
+        let file = temp_file(&[1, 2, 3]);
+        let mut info = open(&file);
+
+        info.insert_byte(1, 0xaa);
+        info.insert_byte(4, 0xbb);
+        info.delete_byte(0).unwrap();
+        assert_eq!(info.file_size, 4);
+        let bytes: Vec<Option<u8>> = (0..5).map(|a| info.get_byte(a).unwrap()).collect();
+        assert_eq!(bytes, vec![Some(0xaa), Some(2), Some(3), Some(0xbb), None]);
+        assert!(info.is_modified(0));
+        assert!(!info.is_modified(1));
+
+        info.undo();
+        info.undo();
+        info.undo();
+        assert_eq!(info.file_size, 3);
+        assert!(!info.has_changes());
+    }
+
+    #[test]
+    fn save_streams_inserted_and_deleted_bytes() {
+        let data: Vec<u8> = (0..600).map(|i| (i % 256) as u8).collect();
+        let file = temp_file(&data);
+        let mut info = open(&file);
+
+        info.delete_byte(0).unwrap();
+        info.insert_byte(300, 0xcc);
+        info.save().unwrap();
+
+        let mut expected = data[1..].to_vec();
+        expected.insert(300, 0xcc);
+        assert_eq!(std::fs::read(&file).unwrap(), expected);
+        assert!(!info.has_changes());
+        assert!(info.undo().is_none());
+        assert_eq!(info.get_byte(300).unwrap(), Some(0xcc));
+    }
+
+    #[test]
+    fn read_at_applies_edits() {
+        let data: Vec<u8> = (0..600).map(|i| (i % 256) as u8).collect();
+        let file = temp_file(&data);
+        let mut info = open(&file);
+
+        info.insert_byte(2, 0xee);
+        info.delete_byte(0).unwrap();
+        let mut buffer = [0; 4];
+        assert_eq!(info.read_at(0, &mut buffer).unwrap(), 4);
+        assert_eq!(buffer, [1, 0xee, 2, 3]);
+        assert_eq!(info.read_at(598, &mut buffer).unwrap(), 2);
+        assert_eq!(buffer[..2], [598 % 256, 599 % 256].map(|v| v as u8));
+    }
+
+    #[test]
+    fn save_writes_modifications() {
+        let data: Vec<u8> = (0..600).map(|i| (i % 256) as u8).collect();
+        let file = temp_file(&data);
+        let mut info = open(&file);
+
+        info.set_byte(0, 0xaa).unwrap();
+        info.set_byte(599, 0xbb).unwrap();
+        info.save().unwrap();
+
+        let saved = std::fs::read(&file).unwrap();
+        assert_eq!(saved.len(), 600);
+        assert_eq!(saved[0], 0xaa);
+        assert_eq!(saved[1..599], data[1..599]);
+        assert_eq!(saved[599], 0xbb);
+        assert!(!info.has_changes());
+        assert_eq!(info.get_byte(599).unwrap(), Some(0xbb));
+    }
+
+    #[test]
+    fn save_as_leaves_original_untouched() {
+        let file = temp_file(&[1, 2, 3]);
+        let target = temp_file(&[]);
+        let mut info = open(&file);
+
+        info.set_byte(2, 9).unwrap();
+        info.save_as(&target.path().to_string_lossy()).unwrap();
+
+        assert_eq!(std::fs::read(&file).unwrap(), vec![1, 2, 3]);
+        assert_eq!(std::fs::read(&target).unwrap(), vec![1, 2, 9]);
+        assert_eq!(info.file_name, target.path().to_string_lossy());
+    }
+
+    #[test]
+    fn scroll_follows_layout() {
+        let file = temp_file(&[0; 100]);
+        let mut info = open(&file);
+        info.set_layout(8, 4);
+
+        info.scroll(MoveValues::DownLine);
+        assert_eq!(info.file_offset, 8);
+        info.scroll(MoveValues::DownPage);
+        assert_eq!(info.file_offset, 40);
+        info.scroll(MoveValues::UpPage);
+        info.scroll(MoveValues::UpPage);
+        assert_eq!(info.file_offset, 0);
+
+        info.set_address(44);
+        info.set_layout(16, 4);
+        assert_eq!(info.file_offset, 32);
+    }
+
+    #[test]
+    fn set_bytes_is_one_step() {
+        let file = temp_file(&[1, 2, 3, 4]);
+        let mut info = open(&file);
+
+        info.set_bytes(1, &[2, 9, 9, 9]).unwrap();
+        assert_eq!(info.file_size, 4);
+        assert_eq!(info.get_byte(3).unwrap(), Some(9));
+
+        let changes = info.undo().unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].offset, 2);
+        assert!(!info.has_changes());
+    }
+
+    #[test]
+    fn write_range_exports_edited_bytes() {
+        let file = temp_file(&[1, 2, 3, 4]);
+        let target = temp_file(&[]);
+        let mut info = open(&file);
+
+        info.insert_bytes(2, &[7, 8]);
+        info.write_range(1, 10, &target.path().to_string_lossy())
+            .unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), vec![2, 7, 8, 3, 4]);
+    }
+
+    #[test]
+    fn missing_and_truncated_files_are_errors() {
+        let path = std::env::temp_dir().join("sedecim_no_such_file");
+        assert!(matches!(
+            SedecimFileInfo::new(path.to_string_lossy().to_string()),
+            Err(SedecimError::Open { .. })
+        ));
+
+        // The default backend reports a file truncated while open.
+        let file = temp_file(&[1; 600]);
+        let mut info = SedecimFileInfo::new(file.path().to_string_lossy().to_string()).unwrap();
+        assert_eq!(info.get_byte(10).unwrap(), Some(1));
+
+        std::fs::write(&file, [1; 100]).unwrap();
+        assert!(matches!(
+            info.get_byte(300),
+            Err(SedecimError::ShortRead { offset: 250, .. })
+        ));
+        assert!(matches!(
+            info.set_byte(500, 2),
+            Err(SedecimError::ShortRead { offset: 500, .. })
+        ));
+        assert!(!info.has_changes());
+    }
+
+    #[test]
+    fn mapped_bytes_are_borrowed_until_edited() {
+        let file = temp_file(b"0123456789");
+        let name = file.path().to_string_lossy().to_string();
+        let mut mapped = SedecimFileInfo::open(name.clone(), BackendKind::Mmap).unwrap();
+        let read = SedecimFileInfo::open(name, BackendKind::Read).unwrap();
+
+        assert_eq!(mapped.borrow_at(2, 4), Some(&b"2345"[..]));
+        assert_eq!(mapped.borrow_at(8, 4), Some(&b"89"[..]));
+        assert_eq!(read.borrow_at(2, 4), None);
+
+        mapped.set_byte(3, b'x').unwrap();
+        assert_eq!(mapped.borrow_at(2, 4), None);
+        assert_eq!(mapped.borrow_at(4, 4), Some(&b"4567"[..]));
+    }
+
+    #[test]
+    fn refuses_to_export_over_the_viewed_file() {
+        let file = temp_file(b"0123456789");
+        let name = file.path().to_string_lossy().to_string();
+        let mapped = SedecimFileInfo::open(name.clone(), BackendKind::Mmap).unwrap();
+
+        assert!(matches!(
+            mapped.write_range(0, 4, &name),
+            Err(SedecimError::Write { .. })
+        ));
+        let link = file.path().with_extension("link");
+        std::fs::hard_link(file.path(), &link).unwrap();
+        let result = mapped.write_range(0, 4, &link.to_string_lossy());
+        std::fs::remove_file(&link).unwrap();
+        assert!(result.is_err());
+        assert_eq!(mapped.borrow_at(0, 10), Some(&b"0123456789"[..]));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn process_memory_is_only_exported_in_bounded_ranges() {
+        let data: Vec<u8> = (0..64).collect();
+        let mut info = SedecimFileInfo::open_process(std::process::id()).unwrap();
+        let target = NamedTempFile::new().unwrap();
+        let target_name = target.path().to_string_lossy().to_string();
+
+        let err = info.save().unwrap_err().to_string();
+        assert!(err.contains("PID data cannot be saved whole"), "{}", err);
+        assert!(info.save_as(&target_name).is_err());
+        assert!(info
+            .write_range(0, MAX_LOAD_SIZE + 1, &target_name)
+            .is_err());
+
+        info.write_range(data.as_ptr() as u64, 64, &target_name)
+            .unwrap();
+        assert_eq!(std::fs::read(target.path()).unwrap(), data);
+    }
+
+    #[test]
+    fn pages_after_the_view_are_prefetched() {
+        let data: Vec<u8> = (0..3000).map(|i| (i % 256) as u8).collect();
+        let file = temp_file(&data);
+        let mut info =
+            SedecimFileInfo::open(file.path().to_string_lossy().to_string(), BackendKind::Read)
+                .unwrap();
+        info.set_layout(16, 16);
+
+        // One screen before the view to two after it is bytes 256 to 1280,
+        // pages 1 to 5.
+        info.set_address(512);
+        let start = std::time::Instant::now();
+        while !info.pages.contains(5) && start.elapsed() < std::time::Duration::from_secs(5) {
+            info.prefetcher.receive(&mut info.pages);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(info.pages.contains(1));
+        assert!(info.pages.contains(5));
+        assert!(!info.pages.contains(6));
+        assert_eq!(info.get_byte(1000).unwrap(), Some((1000 % 256) as u8));
+    }
+
+    /// Process memory with regions at 0x10-0x20, 0x20-0x30 and 0x40-0x50,
+    /// the last one unreadable.
+    struct GappedSource(Vec<MemoryRegion>);
+
+    impl ByteSource for GappedSource {
+        fn len(&self) -> u64 {
+            0x50
+        }
+
+        fn read_at(&self, _offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+            buffer.fill(0);
+            Ok(())
+        }
+
+        fn regions(&self) -> &[MemoryRegion] {
+            &self.0
+        }
+    }
+
+    #[test]
+    fn gaps_between_regions_are_unmapped() {
+        let region = |start, end, permissions: &str| MemoryRegion {
+            start,
+            end,
+            permissions: permissions.to_owned(),
+            path: String::new(),
+        };
+        let source = GappedSource(vec![
+            region(0x10, 0x20, "r-xp"),
+            region(0x20, 0x30, "rw-p"),
+            region(0x40, 0x50, "---p"),
+        ]);
+        let info =
+            SedecimFileInfo::with_source("pid".to_owned(), Box::new(source), BackendKind::Read);
+
+        assert!(!info.is_mapped(0x0f));
+        assert!(info.is_mapped(0x10));
+        assert!(info.is_mapped(0x2f));
+        assert!(!info.is_mapped(0x30));
+        assert!(!info.is_mapped(0x40));
+        assert_eq!(info.mapped_ranges(0, 0x50), [(0x10, 0x30)]);
+        assert_eq!(info.mapped_ranges(0x18, 0x1c), [(0x18, 0x1c)]);
+        assert!(info.mapped_ranges(0x30, 0x50).is_empty());
+    }
+
+    /// Memory whose bytes all hold a value that changes, like a process.
+    struct LiveSource(std::sync::atomic::AtomicU8);
+
+    impl ByteSource for LiveSource {
+        fn len(&self) -> u64 {
+            16
+        }
+
+        fn read_at(&self, _offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+            buffer.fill(self.0.load(std::sync::atomic::Ordering::Relaxed));
+            Ok(())
+        }
+
+        fn is_live(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn live_sources_are_read_again_after_a_refresh() {
+        let source = Arc::new(LiveSource(1.into()));
+        let mut info = SedecimFileInfo::with_source(
+            "pid".to_owned(),
+            Box::new(LiveSource(1.into())),
+            BackendKind::Read,
+        );
+        info.source = source.clone();
+        assert_eq!(info.get_byte(3).unwrap(), Some(1));
+
+        source.0.store(2, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(info.get_byte(3).unwrap(), Some(1));
+        info.refresh();
+        assert_eq!(info.get_byte(3).unwrap(), Some(2));
+    }
+
+    /// A fixed size source that is written in place, like a device.
+    struct DeviceSource(std::sync::Mutex<Vec<u8>>);
+
+    impl ByteSource for DeviceSource {
+        fn len(&self) -> u64 {
+            self.0.lock().unwrap().len() as u64
+        }
+
+        fn read_at(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+            let data = self.0.lock().unwrap();
+            let start = offset as usize;
+            buffer.copy_from_slice(&data[start..start + buffer.len()]);
+            Ok(())
+        }
+
+        fn write_at(&self, offset: u64, data: &[u8]) -> io::Result<()> {
+            let start = offset as usize;
+            self.0.lock().unwrap()[start..start + data.len()].copy_from_slice(data);
+            Ok(())
+        }
+
+        fn saves_in_place(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn devices_save_overwrites_in_place() {
+        let source = Box::new(DeviceSource(std::sync::Mutex::new(vec![1, 2, 3, 4])));
+        let mut info = SedecimFileInfo::with_source("device".to_owned(), source, BackendKind::Read);
+
+        info.set_bytes(1, &[8, 9]).unwrap();
+        info.save().unwrap();
+        assert!(!info.has_changes());
+        let mut buffer = [0; 4];
+        info.read_at(0, &mut buffer).unwrap();
+        assert_eq!(buffer, [1, 8, 9, 4]);
+
+        info.insert_byte(0, 7);
+        assert!(matches!(info.save(), Err(SedecimError::Write { .. })));
+        info.undo();
+        info.delete_byte(3).unwrap();
+        assert!(info.save().is_err());
+    }
+}
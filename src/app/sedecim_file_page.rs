@@ -1,3 +1,6 @@
+use std::io;
+
+use super::byte_source::ByteSource;
 use super::sedecim_file_info::{BUFFER_SIZE, BUFFER_SIZE_U64};
 
 pub struct SedecimFilePage {
@@ -24,6 +27,20 @@ impl SedecimFilePage {
     pub fn get_page(address: u64) -> u64 {
         address / BUFFER_SIZE_U64
     }
+
+    /// Reads page `page_id` from `source`. The last page of the source is
+    /// filled with zeros past its end.
+    pub fn load(source: &dyn ByteSource, page_id: u64) -> io::Result<SedecimFilePage> {
+        let page_start = page_id * BUFFER_SIZE_U64;
+        let length = source.len().saturating_sub(page_start).min(BUFFER_SIZE_U64) as usize;
+
+        let mut page = SedecimFilePage::new();
+        source.read_at(page_start, &mut page.buffer[..length])?;
+        page.loaded = true;
+        page.page_id = page_id;
+        page.page_start = page_start;
+        Ok(page)
+    }
 }
 
 #[cfg(test)]
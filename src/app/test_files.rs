@@ -0,0 +1,26 @@
+//! Temporary files for the unit tests.
+
+use std::io::Write;
+
+use tempfile::NamedTempFile;
+
+use super::sedecim_file_info::SedecimFileInfo;
+
+/// Writes `contents` to a new temporary file, deleted once the returned
+/// handle is dropped.
+pub fn temp_file(contents: &[u8]) -> NamedTempFile {
+    let mut file = tempfile::Builder::new()
+        .prefix("sedecim_")
+        .tempfile()
+        .unwrap();
+    file.write_all(contents).unwrap();
+    file
+}
+
+/// Opens a temporary file holding `contents`, which lasts as long as the
+/// returned handle.
+pub fn open(contents: &[u8]) -> (SedecimFileInfo, NamedTempFile) {
+    let file = temp_file(contents);
+    let info = SedecimFileInfo::new(file.path().to_string_lossy().to_string()).unwrap();
+    (info, file)
+}
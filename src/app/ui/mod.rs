@@ -3,167 +3,357 @@ use std::io::Stdout;
 use tui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, Borders, Clear, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Terminal,
 };
 
 use super::{
-    AppMode,
-    sedecim_file_info::LINE_SIZE,
-    sedecim_file_page::SedecimFilePage,
+    diff::{self, HunkKind},
+    dump::{address_width, display_char},
+    inspector,
+    partitions::format_size,
+    search, AppMode, Columns, SedecimError,
 };
 
+mod theme;
+
+pub use theme::Theme;
+
+/// Width of the data inspector panel, borders included.
+const INSPECTOR_WIDTH: u16 = 50;
+
+/// Fewest bytes per line left beside the inspector when the line size is
+/// fitted to the width.
+const MIN_AUTO_LINE_SIZE: u64 = 16;
+
+/// Width of the partition and memory map lists, borders included.
+const PARTITIONS_WIDTH: u16 = 44;
+
+/// Draws a frame. Bytes that cannot be read are drawn as "??" and the first
+/// read error is returned once the frame is on screen.
 pub fn draw_ui(
     app: &mut super::App,
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), SedecimError> {
+    let mut failure = None;
     terminal
         .draw(|f| {
             let size = f.size();
 
             // Outer border with title
-            let block = Block::default()
-                .title("sedecim")
-                .borders(Borders::ALL);
+            let block = Block::default().title("sedecim").borders(Borders::ALL);
             f.render_widget(block.clone(), size);
 
             let inner = block.inner(size);
 
-            // Layout inside border: main content and status bar
+            let cursor_offset = app.cursor_offset();
+            let status_text = format!(
+                "{}{}{}{}{} | {} bytes | cursor {:06x}{}{} | h - help{}",
+                if app.read_only { "[READ-ONLY] " } else { "" },
+                match app.file_info.source_label() {
+                    Some(label) => format!("[{}] ", label),
+                    None => "".to_owned(),
+                },
+                match app.mode {
+                    AppMode::Edit => "[EDIT] ",
+                    AppMode::Insert => "[INSERT] ",
+                    AppMode::Visual | AppMode::Fill | AppMode::Export => "[VISUAL] ",
+                    _ => "",
+                },
+                app.file_info.display_name(),
+                match &app.compare {
+                    Some(compare) => format!(
+                        "{} vs {}",
+                        if app.file_info.has_changes() {
+                            " *"
+                        } else {
+                            ""
+                        },
+                        compare.file_info.display_name()
+                    ),
+                    None if app.file_info.has_changes() => " *".to_owned(),
+                    None => "".to_owned(),
+                },
+                &app.file_info.file_size,
+                cursor_offset,
+                match app.compare.as_ref() {
+                    Some(compare) if compare.is_alignment_stale(app.file_info.revision()) =>
+                        " (edited since aligned, D aligns again)".to_owned(),
+                    Some(compare) => compare
+                        .current_alignment(app.file_info.revision())
+                        .and_then(|hunks| diff::describe(hunks, cursor_offset))
+                        .map_or_else(String::new, |description| format!(" ({})", description)),
+                    None => "".to_owned(),
+                },
+                match app.selection() {
+                    Some((start, end)) => format!(
+                        " | selection {:06x}-{:06x} ({} bytes)",
+                        start,
+                        end,
+                        end - start + 1
+                    ),
+                    None => "".to_owned(),
+                },
+                if app.status.is_empty() {
+                    "".to_owned()
+                } else {
+                    format!(" | {}", app.status)
+                }
+            );
+
+            // Layout inside border: main content and status bar, which wraps
+            // onto a few more lines when a long message does not fit.
+            let status_lines = (status_text.chars().count() as u16)
+                .div_ceil(inner.width.max(1))
+                .clamp(1, 3);
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+                .constraints([Constraint::Min(1), Constraint::Length(status_lines)].as_ref())
                 .split(inner);
 
-            let byte_count: u64 = LINE_SIZE;
-            let mut lines: Vec<String> = vec![];
-            let mut curr_byte = app.file_info.file_offset;
-            let mut page = app.file_info.get_page(curr_byte);
-            let mut page_line = (curr_byte - page.page_start) / byte_count;
-            for i in 0..20 {
-                if curr_byte > app.file_info.file_size {
-                    continue;
+            let prompt = match app.mode {
+                AppMode::Jump if app.jump_sector => Some(format!(
+                    "Jump to Sector ({} bytes, Tab: address): {}",
+                    app.sector_size(),
+                    app.jump_value
+                )),
+                AppMode::Jump => Some(format!(
+                    "Jump to Address (HEX, Tab: sector): {}",
+                    app.jump_value
+                )),
+                AppMode::Search => Some(format!(
+                    "Search [{}{}] (Tab: encoding, ctrl+t: case): {}",
+                    app.search_kind.label(),
+                    if app.search_ignore_case {
+                        ", ignore case"
+                    } else {
+                        ""
+                    },
+                    app.search_value
+                )),
+                AppMode::SaveAs => Some(format!("Save As: {}", app.save_path)),
+                AppMode::Fill => Some(format!("Fill selection with (HEX): {}", app.fill_value)),
+                AppMode::Export => Some(format!("Export selection to: {}", app.export_path)),
+                AppMode::SavePatch => Some(format!(
+                    "Save pending edits as patch (.ips/.bps/.ups): {}",
+                    app.patch_path
+                )),
+                AppMode::ApplyPatch => {
+                    Some(format!("Apply patch (IPS/BPS/UPS): {}", app.patch_path))
                 }
+                AppMode::ConfirmQuit => Some(
+                    "Unsaved changes. Save before quitting? (y)es / (n)o / (c)ancel".to_owned(),
+                ),
+                _ => None,
+            };
 
-                let page_number = SedecimFilePage::get_page(curr_byte);
-                if page.page_id != page_number {
-                    page = app.file_info.get_page(curr_byte);
-                    page_line = 0;
-                }
-                
-
-                let mut curr_str = format!(" {:06x}  ", curr_byte);
-                let mut char_str = format!(" ");
-                for indx in 0..byte_count {
-                    let ii = ((page_line * byte_count) + indx)   as usize;                    
-                    match app.mode {
-                        AppMode::Standard
-                            if app.selected_line as u64 == i
-                                && app.selected_value as u64 == indx =>
-                        {
-                            curr_str.push_str(&format!("!|{:02x}!| ", page.buffer[ii]));
-
-                            if page.buffer[ii] >= 32 && page.buffer[ii].is_ascii() {
-                                char_str.push_str(&format!("!|{}!| ", page.buffer[ii] as char));
-                            } else {
-                                char_str.push_str("!|.!| ");
-                            }
-                        }
-
-                        _ => {
-                            curr_str.push_str(&format!("{:02x} ", page.buffer[ii]));
-                            if page.buffer[ii] >= 32 && page.buffer[ii].is_ascii() {
-                                char_str.push_str(&format!("{} ", page.buffer[ii] as char));
-                            } else {
-                                char_str.push_str(". ");
-                            }
-                        }
-                    }
-                }
+            let mut content = chunks[0];
+            if let Some(prompt) = prompt {
+                let rows = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(1), Constraint::Length(3)].as_ref())
+                    .split(content);
+                content = rows[0];
+                f.render_widget(draw_prompt(prompt, &app.error, &app.theme), rows[1]);
+            }
 
-                page_line += 1;
-                lines.push(format!("{} | {}", curr_str, char_str));
-                curr_byte += byte_count;
+            if matches!(app.mode, AppMode::Results) {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Min(1), Constraint::Length(24)].as_ref())
+                    .split(content);
+                content = columns[0];
+
+                let items: Vec<ListItem> = app
+                    .search_results
+                    .iter()
+                    .map(|offset| ListItem::new(format!(" {:08x}", offset)))
+                    .collect();
+                let results = List::new(items)
+                    .block(
+                        Block::default()
+                            .title(format!("Matches ({})", app.search_results.len()))
+                            .borders(Borders::ALL),
+                    )
+                    .highlight_style(app.theme.highlight);
+                let mut state = ListState::default();
+                state.select(Some(app.selected_result));
+                f.render_stateful_widget(results, columns[1], &mut state);
             }
 
-            let mut spans: Vec<Spans> = vec![];
-            for l in 0..lines.len() {
-                match app.mode {
-                    AppMode::Standard if app.selected_line == (l as i32) => {
-                        let str_split: Vec<&str> = lines[l].split("!|").collect();
-
-                        let nsp = Spans::from(vec![
-                            Span::styled(str_split[0], Style::default().fg(Color::White)),
-                            Span::styled(
-                                str_split[1],
-                                Style::default()
-                                    .fg(Color::Yellow)
-                                    .add_modifier(Modifier::RAPID_BLINK)
-                                    .add_modifier(Modifier::BOLD)
-                                    .add_modifier(Modifier::UNDERLINED),
-                            ),
-                            Span::styled(str_split[2], Style::default().fg(Color::White)),
-                            Span::styled(
-                                str_split[3],
-                                Style::default()
-                                    .fg(Color::Yellow)
-                                    .add_modifier(Modifier::RAPID_BLINK)
-                                    .add_modifier(Modifier::BOLD)
-                                    .add_modifier(Modifier::UNDERLINED),
-                            ),
-                            Span::styled(str_split[4], Style::default().fg(Color::White)),
-                        ]);
-                        spans.push(nsp);
-                    }
+            if let (AppMode::Partitions, Some(table)) = (&app.mode, &app.partition_table) {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(
+                        [Constraint::Min(1), Constraint::Length(PARTITIONS_WIDTH)].as_ref(),
+                    )
+                    .split(content);
+                content = columns[0];
 
-                    _ => {
-                        let new_span = Spans::from(Span::raw(&lines[l]));
-                        spans.push(new_span);
-                    }
-                }
+                let items: Vec<ListItem> = table
+                    .partitions
+                    .iter()
+                    .map(|partition| {
+                        let kind = if partition.name.is_empty() {
+                            partition.kind.clone()
+                        } else {
+                            format!("{} ({})", partition.name, partition.kind)
+                        };
+                        ListItem::new(vec![
+                            Spans::from(format!(
+                                " {:<3}{:010x} {:>10}",
+                                partition.number,
+                                partition.start,
+                                format_size(partition.length)
+                            )),
+                            Spans::from(format!("    {}", kind)),
+                        ])
+                    })
+                    .collect();
+                let list = List::new(items)
+                    .block(
+                        Block::default()
+                            .title(format!("{} partitions", table.kind.label()))
+                            .borders(Borders::ALL),
+                    )
+                    .highlight_style(app.theme.highlight);
+                let mut state = ListState::default();
+                state.select(Some(app.selected_partition));
+                f.render_stateful_widget(list, columns[1], &mut state);
+            }
+
+            if matches!(app.mode, AppMode::Regions) {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(
+                        [Constraint::Min(1), Constraint::Length(PARTITIONS_WIDTH)].as_ref(),
+                    )
+                    .split(content);
+                content = columns[0];
+
+                let regions = app.file_info.regions();
+                let items: Vec<ListItem> = regions
+                    .iter()
+                    .map(|region| {
+                        let path = if region.path.is_empty() {
+                            "[anonymous]"
+                        } else {
+                            &region.path
+                        };
+                        ListItem::new(vec![
+                            Spans::from(format!(
+                                " {:012x} {:>10} {}",
+                                region.start,
+                                format_size(region.end - region.start),
+                                region.permissions
+                            )),
+                            Spans::from(format!("    {}", path)),
+                        ])
+                    })
+                    .collect();
+                let list = List::new(items)
+                    .block(
+                        Block::default()
+                            .title(format!("Memory map ({})", regions.len()))
+                            .borders(Borders::ALL),
+                    )
+                    .highlight_style(app.theme.highlight);
+                let mut state = ListState::default();
+                state.select(Some(app.selected_region));
+                f.render_stateful_widget(list, columns[1], &mut state);
+            }
+
+            let panes = if app.compare.is_some() { 2 } else { 1 };
+            if app.show_inspector
+                && inspector_fits(content.width, app.columns, app.file_info.file_size, panes)
+            {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Min(1), Constraint::Length(INSPECTOR_WIDTH)].as_ref())
+                    .split(content);
+                content = columns[0];
+                f.render_widget(draw_inspector(app), columns[1]);
             }
 
-            match app.mode {
-                AppMode::Jump => {
-                    let s = format!("Jump to Address (HEX): {}", app.jump_value);
-                    spans.push(Spans::from(Span::raw("".to_owned())));
-                    let mut newspns = Spans::from(vec![
-                        Span::styled(s, Style::default().fg(Color::White)),
-                        Span::styled(
-                            " ",
-                            Style::default()
-                                .add_modifier(Modifier::RAPID_BLINK)
-                                .add_modifier(Modifier::UNDERLINED),
-                        ),
-                    ]);
-
-                    spans.push(newspns);
-
-                    newspns = Spans::from(vec![Span::styled(
-                        app.error.clone(),
-                        Style::default().fg(Color::Red),
-                    )]);
-                    spans.push(newspns);
+            let mut compare_area = None;
+            if app.compare.is_some() {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                    .split(content);
+                content = columns[0];
+                compare_area = Some(columns[1]);
+            }
+
+            let byte_count = match app.columns {
+                Columns::Auto => fit_line_size(content.width, app.file_info.file_size),
+                Columns::Fixed(count) => count,
+            };
+            app.set_layout(byte_count, content.height as u64);
+            app.hex_area = content;
+
+            // The compared file scrolls along with the first one, to the
+            // bytes drawn beside it.
+            let file_offset = app.file_info.file_offset;
+            let page_size = app.file_info.page_size();
+            let revision = app.file_info.revision();
+            if let Some(compare) = app.compare.as_mut() {
+                compare
+                    .file_info
+                    .set_layout(byte_count, content.height as u64);
+                let view_offset = compare.view_offset(revision, file_offset, page_size);
+                compare.file_info.set_address(view_offset);
+            }
+
+            let line_count = app.file_info.visible_lines;
+            let hits = app
+                .search_pattern
+                .as_ref()
+                .and_then(|pattern| {
+                    search::highlight(
+                        &app.file_info,
+                        pattern,
+                        app.file_info.file_offset,
+                        line_count * byte_count,
+                    )
+                    .ok()
+                })
+                .unwrap_or_default();
+
+            let mut spans: Vec<Spans> = vec![];
+            let mut curr_byte = app.file_info.file_offset;
+            for i in 0..line_count {
+                if curr_byte > app.file_info.file_size {
+                    continue;
                 }
 
-                _ => {}
+                let line_hits = hits
+                    .get((i * byte_count) as usize..((i + 1) * byte_count) as usize)
+                    .unwrap_or(&[]);
+                spans.push(draw_line(app, curr_byte, i, line_hits, &mut failure));
+                curr_byte += byte_count;
             }
 
             let para = Paragraph::new(spans).alignment(Alignment::Left);
-            f.render_widget(para, chunks[0]);
+            f.render_widget(para, content);
 
-            let cursor_offset = app.file_info.file_offset
-                + (app.selected_line as u64) * byte_count
-                + (app.selected_value as u64);
-            let status_text = format!(
-                "{} | {} bytes | cursor {:06x} | h - help",
-                &app.file_info.file_name,
-                &app.file_info.file_size,
-                cursor_offset
-            );
-            let status = Paragraph::new(status_text).alignment(Alignment::Left);
+            if let Some(area) = compare_area {
+                let mut spans: Vec<Spans> = vec![];
+                for i in 0..line_count {
+                    let address = app.file_info.file_offset + i * byte_count;
+                    if address > app.file_info.file_size {
+                        continue;
+                    }
+                    spans.push(draw_compare_line(app, address, i, &mut failure));
+                }
+                f.render_widget(Paragraph::new(spans).alignment(Alignment::Left), area);
+            }
+
+            let status = Paragraph::new(status_text)
+                .alignment(Alignment::Left)
+                .wrap(Wrap { trim: false });
             f.render_widget(status, chunks[1]);
 
             if matches!(app.mode, AppMode::Help) {
@@ -172,21 +362,382 @@ pub fn draw_ui(
                     Spans::from("|-------------------|-------------------------|"),
                     Spans::from("| Arrow Keys        | Move Cursor             |"),
                     Spans::from("| Page Up/Page Down | Move Up/Move Down Page  |"),
+                    Spans::from("| Mouse             | Click, drag, scroll     |"),
                     Spans::from("| q                 | Quit/Exit               |"),
-                    Spans::from("| ctrl+g            | Jump to address         |"),
+                    Spans::from("| ctrl+g            | Jump to address/sector  |"),
+                    Spans::from("| / or ctrl+f       | Search hex bytes/text   |"),
+                    Spans::from("| n / N             | Find next/previous      |"),
+                    Spans::from("| l                 | List all matches        |"),
+                    Spans::from("| P                 | List disk partitions    |"),
+                    Spans::from("| M                 | List process memory map |"),
+                    Spans::from("| ] / [             | Next/previous difference|"),
+                    Spans::from("| D                 | Summarize differences   |"),
+                    Spans::from("| A                 | Align compared files    |"),
+                    Spans::from("| c                 | Cycle bytes per line    |"),
+                    Spans::from("| I                 | Toggle data inspector   |"),
+                    Spans::from("| v                 | Visual selection mode   |"),
+                    Spans::from("| y (visual)        | Copy selection          |"),
+                    Spans::from("| p (edit)          | Paste at cursor         |"),
+                    Spans::from("| f (visual)        | Fill selection          |"),
+                    Spans::from("| w (visual)        | Export selection        |"),
+                    Spans::from("| k (visual)        | Checksum selection      |"),
+                    Spans::from("| = (visual)        | Interpret selection     |"),
+                    Spans::from("| ctrl+s            | Save                    |"),
+                    Spans::from("| ctrl+o            | Save as                 |"),
+                    Spans::from("| x                 | Save patch (to 512 MiB) |"),
+                    Spans::from("| X                 | Apply patch (to 512 MiB)|"),
+                    Spans::from("| i                 | Toggle edit mode        |"),
+                    Spans::from("| Insert            | Overwrite/insert mode   |"),
+                    Spans::from("| 0-9 a-f (edit)    | Type byte nibbles       |"),
+                    Spans::from("| Delete (edit)     | Delete byte at cursor   |"),
+                    Spans::from("| Backspace (insert)| Delete previous byte    |"),
+                    Spans::from("| Esc               | Leave edit mode         |"),
+                    Spans::from("| u/ctrl+z          | Undo                    |"),
+                    Spans::from("| ctrl+r/ctrl+y     | Redo                    |"),
                     Spans::from("| h                 | Help                    |"),
                 ];
                 let help = Paragraph::new(help_lines)
                     .block(Block::default().title("Help").borders(Borders::ALL))
                     .alignment(Alignment::Left);
-                let area = centered_rect(60, 40, size);
+                let area = centered_rect(60, 90, size);
                 f.render_widget(Clear, area);
                 f.render_widget(help, area);
             }
         })
-        .expect("Issues");
+        .map_err(SedecimError::Terminal)?;
+
+    match failure {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Builds a one line input prompt followed by any error message.
+fn draw_prompt<'a>(prompt: String, error: &str, theme: &Theme) -> Paragraph<'a> {
+    Paragraph::new(vec![
+        Spans::from(Span::raw("".to_owned())),
+        Spans::from(vec![
+            Span::styled(prompt, theme.plain),
+            Span::styled(
+                " ",
+                Style::default()
+                    .add_modifier(Modifier::RAPID_BLINK)
+                    .add_modifier(Modifier::UNDERLINED),
+            ),
+        ]),
+        Spans::from(vec![Span::styled(error.to_owned(), theme.error)]),
+    ])
+}
+
+/// Builds the data inspector panel decoding the bytes at the cursor.
+fn draw_inspector<'a>(app: &super::App) -> Paragraph<'a> {
+    let offset = app.cursor_offset();
+    let mut data = [0; inspector::INSPECT_SIZE];
+    let read = app.file_info.read_at(offset, &mut data).unwrap_or(0);
+
+    let label_style = app.theme.label;
+    let plain_style = app.theme.plain;
+    let mut lines = vec![Spans::from(Span::styled(
+        format!("{:<8}{:<20}{}", "", "little endian", "big endian"),
+        label_style,
+    ))];
+    for row in inspector::interpret(&data[..read]) {
+        let label = Span::styled(format!("{:<8}", row.label), label_style);
+        match row.big {
+            // Values too wide for two columns get a line each.
+            Some(big) if row.little.len() >= 20 => {
+                lines.push(Spans::from(vec![
+                    label,
+                    Span::styled(row.little, plain_style),
+                ]));
+                lines.push(Spans::from(vec![
+                    Span::raw(format!("{:<8}", "")),
+                    Span::styled(big, plain_style),
+                ]));
+            }
+            Some(big) => lines.push(Spans::from(vec![
+                label,
+                Span::styled(format!("{:<20}{}", row.little, big), plain_style),
+            ])),
+            None => lines.push(Spans::from(vec![
+                label,
+                Span::styled(row.little, plain_style),
+            ])),
+        }
+    }
+
+    Paragraph::new(lines).block(
+        Block::default()
+            .title(format!("Inspector {:06x}", offset))
+            .borders(Borders::ALL),
+    )
+}
+
+/// Maps a terminal cell to the offset of the byte drawn there in either the
+/// hex or the text pane. Cells past the end of the file map to the end.
+pub fn offset_at(app: &super::App, column: u16, row: u16) -> Option<u64> {
+    let area = app.hex_area;
+    if column < area.x || row < area.y || row >= area.bottom() {
+        return None;
+    }
+
+    let line = (row - area.y) as u64;
+    let column = (column - area.x) as u64;
+    let line_size = app.file_info.line_size;
+    let hex_start = address_width(app.file_info.file_size) as u64 + 3;
+    let text_start = hex_start + 3 * line_size + 3;
 
-    Ok(())
+    let index = if column >= hex_start && column < hex_start + 3 * line_size {
+        (column - hex_start) / 3
+    } else if column >= text_start && column < text_start + 2 * line_size {
+        (column - text_start) / 2
+    } else {
+        return None;
+    };
+
+    let offset = app.file_info.file_offset + line * line_size + index;
+    Some(offset.min(app.file_info.file_size))
+}
+
+/// Columns the hex and text panes take for `line_size` bytes per line.
+fn line_width(line_size: u64, file_size: u64) -> u64 {
+    // See `fit_line_size`.
+    address_width(file_size) as u64 + 6 + 5 * line_size
+}
+
+/// Whether the inspector leaves room in `width` columns for `panes` files
+/// side by side, each with the whole of a fixed line size or at least
+/// `MIN_AUTO_LINE_SIZE` bytes. It is hidden otherwise, rather than clipping
+/// the panes.
+fn inspector_fits(width: u16, columns: Columns, file_size: u64, panes: u64) -> bool {
+    let line_size = match columns {
+        Columns::Auto => MIN_AUTO_LINE_SIZE,
+        Columns::Fixed(count) => count,
+    };
+    width as u64 >= INSPECTOR_WIDTH as u64 + panes * line_width(line_size, file_size)
+}
+
+/// Largest number of bytes per line that fits in `width` columns, rounded
+/// down to a multiple of four when there is room for one.
+fn fit_line_size(width: u16, file_size: u64) -> u64 {
+    // " address  " before the hex pane and " | " between the panes, then
+    // "xx " in the hex pane and "c " in the text pane for every byte.
+    let fixed = address_width(file_size) as u64 + 6;
+    let count = (width as u64).saturating_sub(fixed) / 5;
+    if count >= 4 {
+        count - count % 4
+    } else {
+        count.max(1)
+    }
+}
+
+/// Builds one row of the hex and ASCII panes starting at `address`.
+fn draw_line<'a>(
+    app: &mut super::App,
+    address: u64,
+    line: u64,
+    hits: &[bool],
+    failure: &mut Option<SedecimError>,
+) -> Spans<'a> {
+    let selected_style = app.theme.cursor;
+    let modified_style = app.theme.modified;
+    let plain_style = app.theme.plain;
+
+    let show_cursor = matches!(
+        app.mode,
+        AppMode::Standard
+            | AppMode::Edit
+            | AppMode::Insert
+            | AppMode::Visual
+            | AppMode::Fill
+            | AppMode::Export
+            | AppMode::Results
+            | AppMode::Partitions
+            | AppMode::Regions
+    ) && app.selected_line as u64 == line;
+
+    let mut hex_spans = vec![Span::styled(
+        format!(
+            " {:0width$x}  ",
+            address,
+            width = address_width(app.file_info.file_size)
+        ),
+        plain_style,
+    )];
+    let mut char_spans = vec![Span::styled(" | ", plain_style)];
+    for indx in 0..app.file_info.line_size {
+        let byte_address = address + indx;
+        if byte_address < app.file_info.file_size && !app.file_info.is_mapped(byte_address) {
+            let style = if show_cursor && app.selected_value as u64 == indx {
+                selected_style
+            } else {
+                app.theme.unmapped
+            };
+            hex_spans.push(Span::styled("--", style));
+            hex_spans.push(Span::raw(" "));
+            char_spans.push(Span::styled("  ", style));
+            continue;
+        }
+        let value = match app.file_info.get_byte(byte_address) {
+            Ok(value) => value,
+            Err(err) => {
+                hex_spans.push(Span::styled("??", app.theme.error));
+                hex_spans.push(Span::raw(" "));
+                char_spans.push(Span::styled("? ", app.theme.error));
+                failure.get_or_insert(err);
+                continue;
+            }
+        };
+        let Some(value) = value else {
+            // Leave room for the cursor to append at the end of the file.
+            if show_cursor && app.selected_value as u64 == indx {
+                hex_spans.push(Span::styled("  ", selected_style));
+                hex_spans.push(Span::raw(" "));
+            } else {
+                hex_spans.push(Span::raw("   "));
+            }
+            char_spans.push(Span::raw("  "));
+            continue;
+        };
+
+        let mut style = if show_cursor && app.selected_value as u64 == indx {
+            selected_style
+        } else if app.file_info.is_modified(byte_address) {
+            modified_style
+        } else {
+            plain_style
+        };
+        if hits.get(indx as usize).copied().unwrap_or(false) {
+            style = style.patch(app.theme.hit);
+        }
+        if app
+            .selection()
+            .is_some_and(|(start, end)| byte_address >= start && byte_address <= end)
+        {
+            style = style.patch(app.theme.selection);
+        }
+        let revision = app.file_info.revision();
+        if let Some(compare) = app.compare.as_mut() {
+            let patch = match compare.current_alignment(revision) {
+                Some(hunks) => match diff::hunk_at(hunks, byte_address).map(|hunk| hunk.kind) {
+                    Some(HunkKind::Change) => Some(app.theme.diff),
+                    Some(HunkKind::Delete) => Some(app.theme.deleted),
+                    _ => None,
+                },
+                None => compare
+                    .file_info
+                    .get_byte(byte_address)
+                    .map_or(true, |other| other != Some(value))
+                    .then_some(app.theme.diff),
+            };
+            if let Some(patch) = patch {
+                style = style.patch(patch);
+            }
+        }
+
+        let chr = display_char(value);
+
+        hex_spans.push(Span::styled(format!("{:02x}", value), style));
+        hex_spans.push(Span::raw(" "));
+        char_spans.push(Span::styled(chr.to_string(), style));
+        char_spans.push(Span::raw(" "));
+    }
+
+    hex_spans.append(&mut char_spans);
+    Spans::from(hex_spans)
+}
+
+/// Builds one row of the compared file, lined up with the same row of the
+/// file being viewed, with the cursor column marked. Once aligned, each
+/// column shows the byte lined up with the one above it in the first file.
+fn draw_compare_line<'a>(
+    app: &mut super::App,
+    address: u64,
+    line: u64,
+    failure: &mut Option<SedecimError>,
+) -> Spans<'a> {
+    let plain_style = app.theme.plain;
+    let show_cursor = !matches!(app.mode, AppMode::Help) && app.selected_line as u64 == line;
+    let width = address_width(app.file_info.file_size);
+
+    let line_size = app.file_info.line_size;
+    let revision = app.file_info.revision();
+    let Some(compare) = app.compare.as_ref() else {
+        return Spans::default();
+    };
+    let aligned = compare.current_alignment(revision).is_some();
+    // The row starts at the first byte with a counterpart.
+    let row_address = compare.first_counterpart(revision, address, line_size);
+    let mut hex_spans = vec![Span::styled(
+        match row_address {
+            Some(row_address) => format!(" {:0width$x}  ", row_address, width = width),
+            None => " ".repeat(width + 3),
+        },
+        plain_style,
+    )];
+    let mut char_spans = vec![Span::styled(" | ", plain_style)];
+    for indx in 0..line_size {
+        let byte_address = address + indx;
+        let own = app.file_info.get_byte(byte_address).ok().flatten();
+        let Some(compare) = app.compare.as_mut() else {
+            break;
+        };
+        let other_address = compare.counterpart(revision, byte_address);
+        let patch = compare.current_alignment(revision).and_then(|hunks| {
+            if diff::insert_before(hunks, byte_address).is_some() {
+                Some(app.theme.inserted)
+            } else {
+                match diff::hunk_at(hunks, byte_address).map(|hunk| hunk.kind) {
+                    Some(HunkKind::Change) => Some(app.theme.diff),
+                    Some(HunkKind::Delete) => Some(app.theme.deleted),
+                    _ => None,
+                }
+            }
+        });
+        let value = match other_address.map(|other| compare.file_info.get_byte(other)) {
+            Some(Ok(value)) => value,
+            None => None,
+            Some(Err(err)) => {
+                hex_spans.push(Span::styled("??", app.theme.error));
+                hex_spans.push(Span::raw(" "));
+                char_spans.push(Span::styled("? ", app.theme.error));
+                failure.get_or_insert(err);
+                continue;
+            }
+        };
+
+        let mut style = if show_cursor && app.selected_value as u64 == indx {
+            app.theme.cursor
+        } else {
+            plain_style
+        };
+        match patch {
+            Some(patch) => style = style.patch(patch),
+            None if !aligned && value != own => style = style.patch(app.theme.diff),
+            None => {}
+        }
+
+        match value {
+            Some(value) => {
+                hex_spans.push(Span::styled(format!("{:02x}", value), style));
+                char_spans.push(Span::styled(display_char(value).to_string(), style));
+            }
+            // Past the end of the compared file, or deleted from it.
+            None if own.is_some() || patch.is_some() => {
+                hex_spans.push(Span::styled("  ", style));
+                char_spans.push(Span::styled(" ", style));
+            }
+            None => {
+                hex_spans.push(Span::raw("  "));
+                char_spans.push(Span::raw(" "));
+            }
+        }
+        hex_spans.push(Span::raw(" "));
+        char_spans.push(Span::raw(" "));
+    }
+
+    hex_spans.append(&mut char_spans);
+    Spans::from(hex_spans)
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -216,3 +767,19 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 
     horizontal[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inspector_is_hidden_when_the_panes_would_not_fit() {
+        // A 6 digit address, then 5 columns per byte.
+        assert_eq!(line_width(16, 0x1000), 92);
+        assert!(!inspector_fits(80, Columns::Auto, 0x1000, 1));
+        assert!(inspector_fits(142, Columns::Auto, 0x1000, 1));
+        assert!(!inspector_fits(142, Columns::Auto, 0x1000, 2));
+        assert!(!inspector_fits(142, Columns::Fixed(32), 0x1000, 1));
+        assert!(inspector_fits(222, Columns::Fixed(32), 0x1000, 1));
+    }
+}
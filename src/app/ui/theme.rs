@@ -0,0 +1,105 @@
+use tui::style::{Color, Modifier, Style};
+
+/// Styles used to draw the hex view and the panels around it.
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub plain: Style,
+    pub cursor: Style,
+    pub modified: Style,
+    pub label: Style,
+    pub error: Style,
+    /// The selected entry in lists such as the search results.
+    pub highlight: Style,
+    /// Patched over a byte that is part of a search match.
+    pub hit: Style,
+    /// Patched over a byte inside the visual selection.
+    pub selection: Style,
+    /// Process memory outside the mapped regions.
+    pub unmapped: Style,
+    /// Patched over bytes that differ from the file being compared with.
+    pub diff: Style,
+    /// Marks where the compared file has bytes this one lacks.
+    pub inserted: Style,
+    /// Patched over bytes the compared file lacks.
+    pub deleted: Style,
+}
+
+impl Theme {
+    pub const NAMES: [&'static str; 3] = ["dark", "light", "mono"];
+
+    pub fn named(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "mono" => Some(Theme::mono()),
+            _ => None,
+        }
+    }
+
+    /// The original colors, made for terminals with a dark background.
+    pub fn dark() -> Theme {
+        Theme {
+            plain: Style::default().fg(Color::White),
+            cursor: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::RAPID_BLINK)
+                .add_modifier(Modifier::BOLD)
+                .add_modifier(Modifier::UNDERLINED),
+            modified: Style::default().fg(Color::LightRed),
+            label: Style::default().fg(Color::Cyan),
+            error: Style::default().fg(Color::Red),
+            highlight: Style::default().fg(Color::Black).bg(Color::Yellow),
+            hit: Style::default().bg(Color::Blue),
+            selection: Style::default().bg(Color::DarkGray),
+            unmapped: Style::default().fg(Color::DarkGray),
+            diff: Style::default().bg(Color::Red),
+            inserted: Style::default().bg(Color::Green),
+            deleted: Style::default().bg(Color::Magenta),
+        }
+    }
+
+    pub fn light() -> Theme {
+        Theme {
+            plain: Style::default().fg(Color::Black),
+            cursor: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::RAPID_BLINK)
+                .add_modifier(Modifier::BOLD)
+                .add_modifier(Modifier::UNDERLINED),
+            modified: Style::default().fg(Color::Red),
+            label: Style::default().fg(Color::Blue),
+            error: Style::default().fg(Color::Red),
+            highlight: Style::default().fg(Color::White).bg(Color::Blue),
+            hit: Style::default().bg(Color::LightCyan),
+            selection: Style::default().bg(Color::Gray),
+            unmapped: Style::default().fg(Color::Gray),
+            diff: Style::default().bg(Color::LightRed),
+            inserted: Style::default().bg(Color::LightGreen),
+            deleted: Style::default().bg(Color::LightMagenta),
+        }
+    }
+
+    /// No colors at all, only text attributes.
+    pub fn mono() -> Theme {
+        Theme {
+            plain: Style::default(),
+            cursor: Style::default()
+                .add_modifier(Modifier::REVERSED)
+                .add_modifier(Modifier::BOLD),
+            modified: Style::default().add_modifier(Modifier::BOLD),
+            label: Style::default().add_modifier(Modifier::ITALIC),
+            error: Style::default().add_modifier(Modifier::BOLD),
+            highlight: Style::default().add_modifier(Modifier::REVERSED),
+            hit: Style::default().add_modifier(Modifier::UNDERLINED),
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            unmapped: Style::default().add_modifier(Modifier::DIM),
+            diff: Style::default()
+                .add_modifier(Modifier::BOLD)
+                .add_modifier(Modifier::ITALIC),
+            inserted: Style::default()
+                .add_modifier(Modifier::BOLD)
+                .add_modifier(Modifier::UNDERLINED),
+            deleted: Style::default().add_modifier(Modifier::CROSSED_OUT),
+        }
+    }
+}
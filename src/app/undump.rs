@@ -0,0 +1,327 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+
+use thiserror::Error;
+
+/// Problems found while rebuilding a binary from a hex dump. Lines and
+/// columns count from 1.
+#[derive(Debug, Error)]
+pub enum UndumpError {
+    #[error("line {line}, column {column}: '{found}' is not a hex digit")]
+    InvalidDigit {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+
+    #[error("line {line}, column {column}: byte is missing its second hex digit")]
+    OddDigit { line: usize, column: usize },
+
+    #[error("line {line}, column {column}: invalid address '{text}'")]
+    InvalidAddress {
+        line: usize,
+        column: usize,
+        text: String,
+    },
+
+    #[error("line {line}, column {column}: '*' must follow a line of bytes")]
+    UnexpectedRepeat { line: usize, column: usize },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Dump layouts understood when reading, detected from the first line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    /// `00000000: 7f45 4c46  .ELF`
+    Xxd,
+    /// Address, hex bytes and a `|` before the text, as written by
+    /// sedecim's own dump and `hexdump -C`.
+    Table,
+    /// Hex digits only, e.g. `xxd -p` output.
+    Plain,
+}
+
+/// Writes the bytes parsed from a dump, seeking to each line's address.
+struct Rebuild<'a, W: Write + Seek> {
+    output: &'a mut W,
+    position: u64,
+    end: u64,
+    last_line: Vec<u8>,
+    repeating: bool,
+}
+
+impl<W: Write + Seek> Rebuild<'_, W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.output.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        self.end = self.end.max(self.position);
+        Ok(())
+    }
+
+    /// Moves to `address`, first filling a run squeezed into a `*` line
+    /// with copies of the line before it.
+    fn seek(&mut self, address: u64) -> io::Result<()> {
+        if self.repeating && !self.last_line.is_empty() {
+            while self.position < address {
+                let remaining = (address - self.position) as usize;
+                let line = self.last_line.clone();
+                self.write(&line[..remaining.min(line.len())])?;
+            }
+            self.repeating = false;
+        }
+
+        if address != self.position {
+            self.output.seek(SeekFrom::Start(address))?;
+            self.position = address;
+        }
+        Ok(())
+    }
+}
+
+/// Rebuilds the binary described by a hex dump in sedecim, `xxd`,
+/// `hexdump -C` or plain hex format. Bytes are written at the address of
+/// their line, like `xxd -r`. Returns the size of the rebuilt data.
+pub fn undump<R: BufRead, W: Write + Seek>(input: R, output: &mut W) -> Result<u64, UndumpError> {
+    let mut rebuild = Rebuild {
+        output,
+        position: 0,
+        end: 0,
+        last_line: vec![],
+        repeating: false,
+    };
+    let mut format = None;
+    let mut pending: Option<(u8, usize, usize)> = None;
+
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line?;
+        let chars: Vec<char> = line.trim_end().chars().collect();
+        if chars.is_empty() {
+            continue;
+        }
+
+        match *format.get_or_insert_with(|| detect(&chars)) {
+            Format::Plain => {
+                let mut bytes = vec![];
+                for (column, chr) in chars.iter().enumerate() {
+                    if chr.is_whitespace() {
+                        continue;
+                    }
+
+                    let nibble = hex_digit(*chr, line_number, column + 1)?;
+                    match pending.take() {
+                        Some((high, _, _)) => bytes.push((high << 4) | nibble),
+                        None => pending = Some((nibble, line_number, column + 1)),
+                    }
+                }
+                rebuild.write(&bytes)?;
+            }
+
+            Format::Xxd => {
+                let colon = chars.iter().position(|chr| *chr == ':').unwrap_or(0);
+                let address = parse_address(&chars, 0, colon, line_number)?;
+                // The hex ends at the two spaces before the text column.
+                let first = (colon + 1..chars.len())
+                    .find(|at| chars[*at] != ' ')
+                    .unwrap_or(chars.len());
+                let end = (first..chars.len())
+                    .find(|at| chars[*at] == ' ' && chars.get(at + 1) == Some(&' '))
+                    .unwrap_or(chars.len());
+
+                let bytes = parse_tokens(&chars, colon + 1, end, line_number)?;
+                rebuild.seek(address)?;
+                rebuild.write(&bytes)?;
+                rebuild.last_line = bytes;
+            }
+
+            Format::Table => {
+                let start = chars
+                    .iter()
+                    .position(|chr| !chr.is_whitespace())
+                    .unwrap_or(0);
+                if chars[start] == '*' {
+                    if rebuild.last_line.is_empty() {
+                        return Err(UndumpError::UnexpectedRepeat {
+                            line: line_number,
+                            column: start + 1,
+                        });
+                    }
+                    rebuild.repeating = true;
+                    continue;
+                }
+
+                let address_end = (start..chars.len())
+                    .find(|at| chars[*at].is_whitespace())
+                    .unwrap_or(chars.len());
+                let address = parse_address(&chars, start, address_end, line_number)?;
+                let end = (address_end..chars.len())
+                    .find(|at| chars[*at] == '|')
+                    .unwrap_or(chars.len());
+
+                // A line holding only an address marks the end of the data.
+                let bytes = parse_tokens(&chars, address_end, end, line_number)?;
+                rebuild.seek(address)?;
+                rebuild.write(&bytes)?;
+                if !bytes.is_empty() {
+                    rebuild.last_line = bytes;
+                }
+            }
+        }
+    }
+
+    if let Some((_, line, column)) = pending {
+        return Err(UndumpError::OddDigit { line, column });
+    }
+
+    rebuild.output.flush()?;
+    Ok(rebuild.end)
+}
+
+/// Rebuilds the binary described by the dump at `input`, or standard input
+/// for "-", into a new file at `output`.
+pub fn undump_file(input: &str, output: &str) -> Result<u64, UndumpError> {
+    if input == "-" {
+        undump(io::stdin().lock(), &mut File::create(output)?)
+    } else {
+        let reader = BufReader::new(File::open(input)?);
+        undump(reader, &mut File::create(output)?)
+    }
+}
+
+fn detect(chars: &[char]) -> Format {
+    let text: String = chars.iter().collect();
+    let first = text.split_whitespace().next().unwrap_or_default();
+
+    if let Some(address) = first.strip_suffix(':') {
+        if !address.is_empty() && address.chars().all(|chr| chr.is_ascii_hexdigit()) {
+            return Format::Xxd;
+        }
+    }
+
+    if first.len() >= 6 && first.chars().all(|chr| chr.is_ascii_hexdigit()) && text.contains('|') {
+        return Format::Table;
+    }
+
+    Format::Plain
+}
+
+fn hex_digit(chr: char, line: usize, column: usize) -> Result<u8, UndumpError> {
+    chr.to_digit(16)
+        .map(|digit| digit as u8)
+        .ok_or(UndumpError::InvalidDigit {
+            line,
+            column,
+            found: chr,
+        })
+}
+
+fn parse_address(
+    chars: &[char],
+    start: usize,
+    end: usize,
+    line: usize,
+) -> Result<u64, UndumpError> {
+    let text: String = chars[start..end].iter().collect();
+    u64::from_str_radix(text.trim(), 16).map_err(|_| UndumpError::InvalidAddress {
+        line,
+        column: start + 1,
+        text,
+    })
+}
+
+/// Parses the whitespace separated hex tokens in `chars[start..end]`, each
+/// holding one or more whole bytes.
+fn parse_tokens(
+    chars: &[char],
+    start: usize,
+    end: usize,
+    line: usize,
+) -> Result<Vec<u8>, UndumpError> {
+    let mut bytes = vec![];
+    let mut high: Option<(u8, usize)> = None;
+
+    for (column, chr) in chars.iter().enumerate().take(end).skip(start) {
+        if chr.is_whitespace() {
+            if let Some((_, column)) = high {
+                return Err(UndumpError::OddDigit { line, column });
+            }
+            continue;
+        }
+
+        let nibble = hex_digit(*chr, line, column + 1)?;
+        match high.take() {
+            Some((value, _)) => bytes.push((value << 4) | nibble),
+            None => high = Some((nibble, column + 1)),
+        }
+    }
+
+    match high {
+        Some((_, column)) => Err(UndumpError::OddDigit { line, column }),
+        None => Ok(bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn rebuild(text: &str) -> Result<Vec<u8>, UndumpError> {
+        let mut output = Cursor::new(vec![]);
+        undump(text.as_bytes(), &mut output)?;
+        Ok(output.into_inner())
+    }
+
+    #[test]
+    fn reads_xxd() {
+        let text = "00000000: 7f45 4c46 0201 0100 0000 0000 0000 0000  .ELF............\n\
+                    00000010: 6162 63                                  abc\n";
+        let mut expected = b"\x7fELF\x02\x01\x01\x00".to_vec();
+        expected.extend_from_slice(&[0; 8]);
+        expected.extend_from_slice(b"abc");
+
+        assert_eq!(rebuild(text).unwrap(), expected);
+    }
+
+    #[test]
+    fn reads_hexdump_with_repeats() {
+        let text =
+            "00000000  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|\n\
+                    *\n\
+                    00000030  68 65 6c 6c 6f 0a                                 |hello.|\n\
+                    00000036\n";
+        let mut expected = vec![0; 48];
+        expected.extend_from_slice(b"hello\n");
+
+        assert_eq!(rebuild(text).unwrap(), expected);
+    }
+
+    #[test]
+    fn reads_sedecim_and_plain() {
+        let text = " 000000  30 31 32  33 | 0 1 2 3\n 000004  7c 7c        | | |\n";
+        assert_eq!(rebuild(text).unwrap(), b"0123||");
+        assert_eq!(
+            rebuild("dead\nbe ef\n").unwrap(),
+            vec![0xde, 0xad, 0xbe, 0xef]
+        );
+    }
+
+    #[test]
+    fn reports_position_of_bad_input() {
+        match rebuild("00000000: 61zz  a.\n") {
+            Err(UndumpError::InvalidDigit {
+                line: 1,
+                column: 13,
+                found: 'z',
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        match rebuild("ab\nc\n") {
+            Err(UndumpError::OddDigit { line: 2, column: 1 }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
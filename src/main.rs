@@ -1,29 +1,372 @@
-use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+use clap::{Args, Parser, Subcommand};
+
+use app::dump::{self, DumpOptions, DumpStyle};
+use app::patch::{self, PatchFormat};
+use app::{diff, undump, BackendKind, Columns, Theme};
 
 mod app;
 
+const BANNER: &str = r"
+                _              _
+ ___   ___   __| |  ___   ___ (_) _ __ ___
+/ __| / _ \ / _` | / _ \ / __|| || '_ ` _ \
+\__ \|  __/| (_| ||  __/| (__ | || | | | | |
+|___/ \___| \__,_| \___| \___||_||_| |_| |_|
+";
+
+const EXAMPLES: &str = "\
+Examples:
+  sedecim research.txt
+  sedecim --offset 0x200 --cols 32 --read-only research.txt
+  sedecim --pid 1234
+  sedecim --compare firmware-v2.bin firmware-v1.bin
+  sedecim dump --style xxd research.txt
+  sedecim diff firmware-v1.bin firmware-v2.bin
+  sedecim patch create firmware-v1.bin firmware-v2.bin fix.bps
+  sedecim patch apply fix.bps firmware.bin --output firmware-fixed.bin
+  curl -s https://example.com/firmware.bin | sedecim -
+  sedecim dump research.txt | sedecim undump - copy.txt";
+
+/// CLI hex viewer and editor.
+#[derive(Parser)]
+#[command(
+    name = "sedecim",
+    version,
+    before_help = BANNER,
+    after_help = EXAMPLES,
+    arg_required_else_help = true,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    view: ViewArgs,
+}
+
+#[derive(Args)]
+struct ViewArgs {
+    /// File to open in the viewer, or - to read standard input.
+    file: Option<String>,
+
+    /// View the memory of a running process at its virtual addresses,
+    /// read-only.
+    #[arg(long, value_name = "PID", conflicts_with = "file")]
+    pid: Option<u32>,
+
+    /// Show a second file beside the first, highlighting the bytes that
+    /// differ.
+    #[arg(long, value_name = "FILE", conflicts_with = "pid")]
+    compare: Option<String>,
+
+    /// Offset to place the cursor on, decimal or hex with a 0x prefix.
+    #[arg(long, value_name = "N", value_parser = parse_number)]
+    offset: Option<u64>,
+
+    /// Open the file without allowing any changes.
+    #[arg(long)]
+    read_only: bool,
+
+    /// Bytes per line: auto to fit the terminal, or a count from 1 to 256.
+    #[arg(long, value_name = "auto|N", default_value = "auto", value_parser = parse_columns)]
+    cols: Columns,
+
+    /// Color theme.
+    #[arg(long, default_value = "dark", value_parser = Theme::NAMES)]
+    theme: String,
+
+    /// How the file is read: read seeks and reads every page; mmap maps it
+    /// into memory, which is faster but crashes if the file is truncated
+    /// while open, and falls back to read for files that cannot be mapped.
+    #[arg(long, default_value = "read", value_parser = BackendKind::NAMES)]
+    backend: String,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the file as rows of hex and text instead of opening the viewer.
+    // `--dump` is the spelling from before there were subcommands.
+    #[command(long_flag_alias = "dump")]
+    Dump {
+        /// Layout of the rows.
+        #[arg(long, default_value = "sedecim", value_parser = parse_style)]
+        style: DumpStyle,
+
+        /// First byte to print, decimal or hex with a 0x prefix.
+        #[arg(long, value_name = "N", default_value = "0", value_parser = parse_number)]
+        offset: u64,
+
+        /// Number of bytes to print.
+        #[arg(long, value_name = "N", value_parser = parse_number)]
+        length: Option<u64>,
+
+        /// Bytes per line.
+        #[arg(long, value_name = "N", default_value_t = 16,
+              value_parser = clap::value_parser!(u64).range(1..=256))]
+        cols: u64,
+
+        /// Bytes per group, 0 for none. Defaults to the style's own grouping.
+        #[arg(long, value_name = "N")]
+        group: Option<usize>,
+
+        /// File to print, or - to read standard input.
+        file: String,
+    },
+
+    /// Rebuild a binary from a sedecim, xxd, hexdump -C or plain hex dump.
+    Undump {
+        /// Dump to read, or - for standard input.
+        input: String,
+        /// File to write the rebuilt binary to.
+        output: String,
+    },
+
+    /// Align two files, allowing for inserted and deleted bytes, and list
+    /// where they differ.
+    Diff {
+        /// Also list the stretches that match.
+        #[arg(long)]
+        all: bool,
+
+        /// Original file, or - to read standard input.
+        old: String,
+        /// Changed file.
+        new: String,
+    },
+
+    /// Create or apply IPS, BPS and UPS patches.
+    Patch {
+        #[command(subcommand)]
+        action: PatchAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum PatchAction {
+    /// Write a patch that turns OLD into NEW.
+    Create {
+        /// Patch format. Defaults to the extension of the patch file.
+        #[arg(long, value_parser = parse_patch_format)]
+        format: Option<PatchFormat>,
+
+        /// Original file.
+        old: String,
+        /// Changed file.
+        new: String,
+        /// Patch file to write.
+        patch: String,
+    },
+
+    /// Apply a patch, checking the BPS and UPS checksums of the file before
+    /// and after.
+    Apply {
+        /// IPS, BPS or UPS patch, recognised from its contents.
+        patch: String,
+        /// File to patch.
+        file: String,
+
+        /// Write the patched file here instead of over FILE.
+        #[arg(long, value_name = "FILE")]
+        output: Option<String>,
+    },
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Some(Command::Dump {
+            style,
+            offset,
+            length,
+            cols,
+            group,
+            file,
+        }) => {
+            let mut options = DumpOptions::new();
+            options.style = style;
+            options.offset = offset;
+            options.length = length;
+            options.cols = cols as usize;
+            options.group = group;
+            run_dump(&file, &options)
+        }
+        Some(Command::Undump { input, output }) => undump::undump_file(&input, &output)
+            .map(|_| ())
+            .map_err(|err| format!("{}: {}", input, err)),
+        Some(Command::Diff { all, old, new }) => run_diff(&old, &new, all),
+        Some(Command::Patch { action }) => run_patch(action),
+        None => run_view(cli.view),
+    };
+
+    if let Err(err) = result {
+        eprintln!("sedecim: {}", err);
+        process::exit(1);
+    }
+}
+
+/// Opens the viewer once the file and options are known to be usable.
+fn run_view(args: ViewArgs) -> Result<(), String> {
+    // Clap asks for a file or a subcommand before getting here.
+    let file_name = match (args.file, args.pid) {
+        (Some(file_name), _) => file_name,
+        (None, Some(pid)) => format!("/proc/{}/mem", pid),
+        (None, None) => return Err("a file name or --pid is required".to_owned()),
+    };
+    if args.pid.is_none() {
+        check_file(&file_name)?;
+    }
+    if let Some(compare) = &args.compare {
+        check_file(compare)?;
+    }
+
+    let offset = args.offset.unwrap_or(0);
+    let options = app::Options {
+        file_name,
+        pid: args.pid,
+        compare: args.compare,
+        offset,
+        // Writing into a running process is never wanted by accident.
+        read_only: args.read_only || args.pid.is_some(),
+        columns: args.cols,
+        theme: Theme::named(&args.theme).unwrap_or_else(Theme::dark),
+        backend: BackendKind::parse(&args.backend).unwrap_or_default(),
+    };
+    let mut app = app::App::new(options).map_err(|err| err.to_string())?;
+
+    // The size of piped input is only known once it has been read.
+    let file_size = app.file_info.file_size;
+    if offset > 0 && offset >= file_size {
+        return Err(format!(
+            "offset {:#x} is past the end of {} ({} bytes)",
+            offset,
+            app.file_info.display_name(),
+            file_size
+        ));
+    }
+
+    app.run().map_err(|err| err.to_string())
+}
 
-    if args.len() == 1 {
-        show_help();
-        return;
+/// Prints the file as rows of hex and text instead of opening the viewer.
+fn run_dump(file_name: &str, options: &DumpOptions) -> Result<(), String> {
+    check_file(file_name)?;
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    match dump::dump_file(file_name, options, &mut out).and_then(|_| out.flush()) {
+        // The reader went away, e.g. `sedecim dump file | head`.
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.map_err(|err| err.to_string()),
     }
+}
 
-    let mut app = app::App::new(args);
+/// Prints where two files differ once they are aligned.
+fn run_diff(old: &str, new: &str, all: bool) -> Result<(), String> {
+    check_file(old)?;
+    check_file(new)?;
 
-    app.run();
+    let mut out = BufWriter::new(io::stdout().lock());
+    match diff::diff_files(old, new, all, &mut out).and_then(|_| out.flush()) {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.map_err(|err| err.to_string()),
+    }
 }
 
-fn show_help() {
-    println!("\n\n");
-    println!("                _              _            ");
-    println!(" ___   ___   __| |  ___   ___ (_) _ __ ___  ");
-    println!("/ __| / _ \\ / _` | / _ \\ / __|| || '_ ` _ \\ ");
-    println!("\\__ \\|  __/| (_| ||  __/| (__ | || | | | | |");
-    println!("|___/ \\___| \\__,_| \\___| \\___||_||_| |_| |_|\n\n");
+/// Creates or applies a patch.
+fn run_patch(action: PatchAction) -> Result<(), String> {
+    let result = match action {
+        PatchAction::Create {
+            format,
+            old,
+            new,
+            patch,
+        } => {
+            check_file(&old)?;
+            check_file(&new)?;
+            patch::create_file(format, &old, &new, &patch)
+        }
+        PatchAction::Apply {
+            patch,
+            file,
+            output,
+        } => {
+            check_file(&patch)?;
+            check_file(&file)?;
+            patch::apply_file(&patch, &file, output.as_deref())
+        }
+    };
+
+    result.map(|_| ()).map_err(|err| err.to_string())
+}
 
-    println!("File Name is Required!\n");
-    println!("sedecim <File Name>");
-    println!("Example: sedecim research.txt");
+/// Makes sure `file_name` is a file that can be read, or "-" for standard
+/// input, so problems are reported before the terminal is taken over.
+fn check_file(file_name: &str) -> Result<(), String> {
+    if file_name == "-" {
+        return Ok(());
+    }
+
+    let metadata = fs::metadata(file_name).map_err(|err| format!("{}: {}", file_name, err))?;
+    if metadata.is_dir() {
+        return Err(format!("{}: is a directory", file_name));
+    }
+    // Opening a pipe here would take data meant for the viewer.
+    if metadata.is_file() {
+        File::open(file_name).map_err(|err| format!("{}: {}", file_name, err))?;
+    }
+
+    Ok(())
+}
+
+/// Parses a number in decimal or as hex with a `0x` prefix.
+fn parse_number(value: &str) -> Result<u64, String> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+
+    parsed.map_err(|_| format!("'{}' is not a decimal or 0x hex number", value))
+}
+
+fn parse_columns(value: &str) -> Result<Columns, String> {
+    if value == "auto" {
+        return Ok(Columns::Auto);
+    }
+
+    match value.parse() {
+        Ok(count) if (1..=256).contains(&count) => Ok(Columns::Fixed(count)),
+        _ => Err(format!("'{}' is not auto or a count from 1 to 256", value)),
+    }
+}
+
+fn parse_patch_format(value: &str) -> Result<PatchFormat, String> {
+    PatchFormat::parse(value).ok_or_else(|| format!("'{}' is not ips, bps or ups", value))
+}
+
+fn parse_style(value: &str) -> Result<DumpStyle, String> {
+    DumpStyle::parse(value).ok_or_else(|| format!("'{}' is not sedecim, xxd or hexdump", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_is_a_subcommand_and_a_flag() {
+        for args in [
+            ["sedecim", "dump", "--cols", "8", "research.txt"],
+            ["sedecim", "--dump", "--cols", "8", "research.txt"],
+        ] {
+            let cli = Cli::try_parse_from(args).unwrap();
+            assert!(matches!(
+                cli.command,
+                Some(Command::Dump { cols: 8, ref file, .. }) if file == "research.txt"
+            ));
+        }
+    }
 }